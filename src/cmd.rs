@@ -1,3 +1,9 @@
 pub mod aws;
 pub mod azure;
+pub mod cloud;
+pub mod gcp;
+pub mod gws;
+pub mod k8s;
+pub mod okta;
 pub mod update;
+pub mod validate_rules;
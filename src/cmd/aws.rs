@@ -1,4 +1,7 @@
 pub mod aws_metrics;
+pub mod aws_s3_timeline;
 pub mod aws_search;
 pub mod aws_summary;
 pub mod aws_timeline;
+pub mod aws_vpc_summary;
+pub mod aws_vpc_timeline;
@@ -1,6 +1,8 @@
 use crate::core::color::SuzakuColor::Red;
 use crate::core::log_source::LogSource;
-use crate::core::scan::{get_content, load_json_from_file, process_events_from_dir};
+use crate::core::scan::{
+    process_events_from_dir, process_events_from_remote, remote_input_url, stream_events_from_file,
+};
 use crate::core::util::{fatal_error, get_writer, output_path_info, p, sanitize_csv_field};
 use crate::option::cli::InputOption;
 use crate::option::timefiler::filter_by_time;
@@ -49,6 +51,8 @@ pub fn aws_metrics(input_opt: &InputOption, field: &str, output: &Option<PathBuf
             no_color,
             &LogSource::Aws,
             &input_opt.file_date_opt,
+            None,
+            false,
         ) {
             p(
                 Red.rdg(no_color),
@@ -57,13 +61,27 @@ pub fn aws_metrics(input_opt: &InputOption, field: &str, output: &Option<PathBuf
             );
         }
         print_count_map_desc(csv_header, &count_map, wtr, output, no_color);
+    } else if let Some(url) = remote_input_url(input_opt) {
+        if let Err(e) = process_events_from_remote(
+            stats_func,
+            input_opt,
+            true,
+            no_color,
+            &LogSource::Aws,
+            false,
+        ) {
+            p(
+                Red.rdg(no_color),
+                &format!("Failed to scan {url}: {e}"),
+                true,
+            );
+        }
+        print_count_map_desc(csv_header, &count_map, wtr, output, no_color);
     } else if let Some(f) = file {
-        let log_contents = get_content(f);
-        let events = load_json_from_file(&log_contents, &LogSource::Aws);
-        if let Ok(events) = events {
-            stats_func(&events);
-            print_count_map_desc(csv_header, &count_map, wtr, output, no_color);
+        if let Err(e) = stream_events_from_file(f, &LogSource::Aws, &mut stats_func, &|_| {}) {
+            eprintln!("[WARNING] Stopped reading {}: {e}", f.display());
         }
+        print_count_map_desc(csv_header, &count_map, wtr, output, no_color);
     }
 }
 
@@ -1,6 +1,8 @@
 use crate::core::color::SuzakuColor::{Green, Red};
 use crate::core::log_source::LogSource;
-use crate::core::scan::{get_content, load_json_from_file, process_events_from_dir};
+use crate::core::scan::{
+    process_events_from_dir, process_events_from_remote, remote_input_url, stream_events_from_file,
+};
 use crate::core::timeline_writer::{OutputConfig, OutputContext, init_writers, write_record};
 use crate::core::util::{fatal_error, load_profile, output_path_info, p};
 use crate::option::cli::{CommonOptions, SearchOptions};
@@ -11,7 +13,9 @@ use regex::Regex;
 use serde_json::Value;
 use sigma_rust::{event_from_json, rule_from_yaml};
 
-pub fn aws_search(options: &SearchOptions, common_opt: &CommonOptions) {
+/// Search CloudTrail (`aws-ct-search`), VPC Flow Logs (`aws-vpc-search`) or S3 server access log
+/// (`aws-s3-search`) events, as selected by `log`.
+pub fn aws_search(options: &SearchOptions, common_opt: &CommonOptions, log: &LogSource) {
     let no_color = common_opt.no_color;
     let directory = &options.input_opt.directory;
     let file = &options.input_opt.filepath;
@@ -33,10 +37,11 @@ pub fn aws_search(options: &SearchOptions, common_opt: &CommonOptions) {
         }
     }
 
-    let profile = load_profile(&LogSource::Aws, &geo_search, true);
+    let profile = load_profile(log, &geo_search, true);
     let (writers, output_pathes) = init_writers(
         options.output_opt.output.as_ref(),
         options.output_opt.output_type,
+        false,
     )
     .unwrap_or_else(|e| fatal_error(no_color, &e));
     let config = OutputConfig::new(no_color, options.output_opt.raw_output, false);
@@ -129,8 +134,10 @@ level: informational";
             d,
             options.output_opt.output.is_some(),
             no_color,
-            &LogSource::Aws,
+            log,
             &options.input_opt.file_date_opt,
+            None,
+            false,
         ) {
             p(
                 Red.rdg(no_color),
@@ -138,12 +145,25 @@ level: informational";
                 true,
             );
         }
-    } else if let Some(f) = file {
-        let log_contents = get_content(f);
-        let events = load_json_from_file(&log_contents, &LogSource::Aws);
-        if let Ok(events) = events {
-            search_func(&events);
+    } else if let Some(url) = remote_input_url(&options.input_opt) {
+        if let Err(e) = process_events_from_remote(
+            search_func,
+            &options.input_opt,
+            options.output_opt.output.is_some(),
+            no_color,
+            log,
+            false,
+        ) {
+            p(
+                Red.rdg(no_color),
+                &format!("Failed to scan {url}: {e}"),
+                true,
+            );
         }
+    } else if let Some(f) = file
+        && let Err(e) = stream_events_from_file(f, log, &mut search_func, &|_| {})
+    {
+        eprintln!("[WARNING] Stopped reading {}: {e}", f.display());
     }
 
     display_results(matched_events, total_events, no_color);
@@ -1,6 +1,8 @@
 use crate::core::color::SuzakuColor::Red;
 use crate::core::log_source::LogSource;
-use crate::core::scan::{get_content, load_json_from_file, process_events_from_dir};
+use crate::core::scan::{
+    process_events_from_dir, process_events_from_remote, remote_input_url, stream_events_from_file,
+};
 use crate::core::util::{fatal_error, get_writer, output_path_info, p, sanitize_csv_field};
 use crate::option::cli::InputOption;
 use crate::option::geoip::GeoIPSearch;
@@ -373,6 +375,8 @@ pub fn aws_summary(
             no_color,
             &LogSource::Aws,
             &input_opt.file_date_opt,
+            None,
+            false,
         ) {
             p(
                 Red.rdg(no_color),
@@ -389,21 +393,43 @@ pub fn aws_summary(
             output_type,
             clobber,
         );
-    } else if let Some(f) = file {
-        let log_contents = get_content(f);
-        let events = load_json_from_file(&log_contents, &LogSource::Aws);
-        if let Ok(events) = events {
-            summary_func(&events);
-            output_summary(
-                &user_data,
-                output,
-                no_color,
-                hide_descriptions,
-                abused_aws_api_values,
-                output_type,
-                clobber,
+    } else if let Some(url) = remote_input_url(input_opt) {
+        if let Err(e) = process_events_from_remote(
+            summary_func,
+            input_opt,
+            true,
+            no_color,
+            &LogSource::Aws,
+            false,
+        ) {
+            p(
+                Red.rdg(no_color),
+                &format!("Failed to scan {url}: {e}"),
+                true,
             );
         }
+        output_summary(
+            &user_data,
+            output,
+            no_color,
+            hide_descriptions,
+            abused_aws_api_values,
+            output_type,
+            clobber,
+        );
+    } else if let Some(f) = file {
+        if let Err(e) = stream_events_from_file(f, &LogSource::Aws, &mut summary_func, &|_| {}) {
+            eprintln!("[WARNING] Stopped reading {}: {e}", f.display());
+        }
+        output_summary(
+            &user_data,
+            output,
+            no_color,
+            hide_descriptions,
+            abused_aws_api_values,
+            output_type,
+            clobber,
+        );
     }
 }
 
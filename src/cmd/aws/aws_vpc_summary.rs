@@ -0,0 +1,216 @@
+use crate::core::color::SuzakuColor::Red;
+use crate::core::log_source::LogSource;
+use crate::core::scan::{
+    process_events_from_dir, process_events_from_remote, remote_input_url, stream_events_from_file,
+};
+use crate::core::util::{fatal_error, get_writer, output_path_info, p, sanitize_csv_field};
+use crate::option::cli::InputOption;
+use crate::option::timefiler::filter_by_time;
+use comfy_table::{Cell, CellAlignment, Table};
+use num_format::{Locale, ToFormattedString};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use termcolor::Color;
+
+/// Traffic totals for one source/destination address pair.
+#[derive(Default, Debug, PartialEq)]
+struct FlowSummary {
+    flows: u64,
+    packets: u64,
+    bytes: u64,
+    accepted: u64,
+    rejected: u64,
+    first_seen: String,
+    last_seen: String,
+}
+
+impl FlowSummary {
+    fn add_record(&mut self, record: &Value) {
+        let num = |field: &str| record.get(field).and_then(Value::as_u64).unwrap_or(0);
+        self.flows += 1;
+        self.packets += num("packets");
+        self.bytes += num("bytes");
+        match record.get("action").and_then(Value::as_str) {
+            Some("ACCEPT") => self.accepted += 1,
+            Some("REJECT") => self.rejected += 1,
+            _ => {}
+        }
+        if let Some(time) = record.get("eventTime").and_then(Value::as_str) {
+            if self.first_seen.is_empty() || time < self.first_seen.as_str() {
+                self.first_seen = time.to_string();
+            }
+            if time > self.last_seen.as_str() {
+                self.last_seen = time.to_string();
+            }
+        }
+    }
+}
+
+/// Aggregate flow records per `(srcaddr, dstaddr)`. Records without both addresses
+/// (`NODATA`/`SKIPDATA`) are skipped.
+fn summarize_flows(records: &[Value], summaries: &mut HashMap<(String, String), FlowSummary>) {
+    for record in records {
+        let (Some(src), Some(dst)) = (
+            record.get("srcaddr").and_then(Value::as_str),
+            record.get("dstaddr").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        summaries
+            .entry((src.to_string(), dst.to_string()))
+            .or_default()
+            .add_record(record);
+    }
+}
+
+pub fn aws_vpc_summary(input_opt: &InputOption, output: &Option<PathBuf>, no_color: bool) {
+    let directory = &input_opt.directory;
+    let file = &input_opt.filepath;
+    let mut summaries: HashMap<(String, String), FlowSummary> = HashMap::new();
+    let mut summary_func = |json_values: &[Value]| {
+        let records: Vec<Value> = json_values
+            .iter()
+            .filter(|v| filter_by_time(&input_opt.time_opt, v, "eventTime"))
+            .cloned()
+            .collect();
+        summarize_flows(&records, &mut summaries);
+    };
+
+    if let Some(d) = directory {
+        if let Err(e) = process_events_from_dir(
+            summary_func,
+            d,
+            true,
+            no_color,
+            &LogSource::VpcFlow,
+            &input_opt.file_date_opt,
+            None,
+            false,
+        ) {
+            p(
+                Red.rdg(no_color),
+                &format!("Failed to scan directory {}: {e}", d.display()),
+                true,
+            );
+        }
+    } else if let Some(url) = remote_input_url(input_opt) {
+        if let Err(e) = process_events_from_remote(
+            summary_func,
+            input_opt,
+            true,
+            no_color,
+            &LogSource::VpcFlow,
+            false,
+        ) {
+            p(
+                Red.rdg(no_color),
+                &format!("Failed to scan {url}: {e}"),
+                true,
+            );
+        }
+    } else if let Some(f) = file
+        && let Err(e) = stream_events_from_file(f, &LogSource::VpcFlow, &mut summary_func, &|_| {})
+    {
+        eprintln!("[WARNING] Stopped reading {}: {e}", f.display());
+    }
+    print_flow_summary(&summaries, output, no_color);
+}
+
+fn print_flow_summary(
+    summaries: &HashMap<(String, String), FlowSummary>,
+    output: &Option<PathBuf>,
+    no_color: bool,
+) {
+    if summaries.is_empty() {
+        p(Some(Color::Rgb(255, 0, 0)), "No events found.", true);
+        return;
+    }
+    let csv_header = vec![
+        "SrcAddr",
+        "DstAddr",
+        "Flows",
+        "Packets",
+        "Bytes",
+        "Accepted",
+        "Rejected",
+        "FirstSeen",
+        "LastSeen",
+    ];
+    let mut wtr = get_writer(output).unwrap_or_else(|e| fatal_error(no_color, &e));
+    let header_cells: Vec<Cell> = csv_header
+        .iter()
+        .map(|s| Cell::new(s).set_alignment(CellAlignment::Center))
+        .collect();
+    let mut table = Table::new();
+    table.set_header(header_cells);
+    if output.is_some() {
+        wtr.write_record(&csv_header).unwrap();
+    }
+
+    // Largest transfers first, so likely exfiltration is at the top.
+    let mut sorted: Vec<_> = summaries.iter().collect();
+    sorted.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(b.0)));
+    for ((src, dst), summary) in sorted {
+        let format_time = |t: &str| t.replace('T', " ").replace('Z', "");
+        let record = [
+            src.clone(),
+            dst.clone(),
+            summary.flows.to_formatted_string(&Locale::en),
+            summary.packets.to_formatted_string(&Locale::en),
+            summary.bytes.to_formatted_string(&Locale::en),
+            summary.accepted.to_formatted_string(&Locale::en),
+            summary.rejected.to_formatted_string(&Locale::en),
+            format_time(&summary.first_seen),
+            format_time(&summary.last_seen),
+        ];
+        if output.is_none() {
+            table.add_row(record.iter().map(Cell::new));
+        } else {
+            let sanitized: Vec<String> = record.iter().map(|f| sanitize_csv_field(f)).collect();
+            wtr.write_record(&sanitized).unwrap();
+        }
+    }
+    wtr.flush().ok();
+    match output {
+        Some(csv) => output_path_info(no_color, [csv.clone()].as_slice(), true),
+        None => println!("{table}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summarizes_bytes_and_packets_per_address_pair() {
+        let records = vec![
+            json!({"srcaddr": "10.0.1.5", "dstaddr": "198.51.100.7", "packets": 10, "bytes": 1000,
+                   "action": "ACCEPT", "eventTime": "2024-01-15T03:05:00Z"}),
+            json!({"srcaddr": "10.0.1.5", "dstaddr": "198.51.100.7", "packets": 5, "bytes": 500,
+                   "action": "ACCEPT", "eventTime": "2024-01-15T03:04:00Z"}),
+            json!({"srcaddr": "203.0.113.12", "dstaddr": "10.0.1.5", "packets": 1, "bytes": 40,
+                   "action": "REJECT", "eventTime": "2024-01-15T03:06:00Z"}),
+            json!({"interface-id": "eni-0a1b2c3d", "log-status": "NODATA"}),
+        ];
+        let mut summaries = HashMap::new();
+        summarize_flows(&records, &mut summaries);
+        assert_eq!(summaries.len(), 2);
+        let out = &summaries[&("10.0.1.5".to_string(), "198.51.100.7".to_string())];
+        assert_eq!(
+            out,
+            &FlowSummary {
+                flows: 2,
+                packets: 15,
+                bytes: 1500,
+                accepted: 2,
+                rejected: 0,
+                first_seen: "2024-01-15T03:04:00Z".to_string(),
+                last_seen: "2024-01-15T03:05:00Z".to_string(),
+            }
+        );
+        let inbound = &summaries[&("203.0.113.12".to_string(), "10.0.1.5".to_string())];
+        assert_eq!(inbound.rejected, 1);
+    }
+}
@@ -0,0 +1 @@
+pub mod gcp_timeline;
@@ -0,0 +1 @@
+pub mod gws_timeline;
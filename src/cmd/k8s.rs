@@ -0,0 +1 @@
+pub mod k8s_timeline;
@@ -0,0 +1 @@
+pub mod okta_timeline;
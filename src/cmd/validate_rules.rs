@@ -0,0 +1,286 @@
+use crate::core::color::SuzakuColor::{Green, Red};
+use crate::core::log_source::LogSource;
+use crate::core::util::p;
+use num_format::{Locale, ToFormattedString};
+use serde::Deserialize;
+use sigma_rust::{parse_rules_from_yaml, rule_from_yaml};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use yaml_serde::Value;
+
+/// A reason a rule file would be skipped, or would not work, when the timeline commands load it.
+#[derive(Debug, PartialEq)]
+pub struct RuleIssue {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// `validate-rules`: check every rule file under `rules` and print the problems found. Returns
+/// false if there were any, so the command can exit non-zero.
+pub fn validate_rules(rules: &Path, no_color: bool) -> bool {
+    if !rules.exists() {
+        p(
+            Red.rdg(no_color),
+            &format!("Rule file or directory does not exist: {}", rules.display()),
+            true,
+        );
+        return false;
+    }
+    let files = rule_files(rules);
+    let issues = check_rule_files(&files);
+    for issue in &issues {
+        p(Red.rdg(no_color), "[ERROR] ", false);
+        p(
+            None,
+            &format!("{}: {}", issue.path.display(), issue.message),
+            true,
+        );
+    }
+    if !issues.is_empty() {
+        println!();
+    }
+    let failed: HashSet<&PathBuf> = issues.iter().map(|issue| &issue.path).collect();
+    p(Green.rdg(no_color), "Rule files checked: ", false);
+    p(None, &files.len().to_formatted_string(&Locale::en), true);
+    p(Green.rdg(no_color), "Rule files with errors: ", false);
+    p(None, &failed.len().to_formatted_string(&Locale::en), true);
+    issues.is_empty()
+}
+
+/// The `.yml` files under `rules`, plus any `.yaml` files (which the loader silently ignores), in
+/// path order.
+fn rule_files(rules: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if rules.is_file() {
+        files.push(rules.to_path_buf());
+    } else {
+        collect_rule_files(rules, &mut files);
+    }
+    files.sort();
+    files
+}
+
+fn collect_rule_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_rule_files(&path, files);
+        } else if matches!(
+            path.extension().and_then(|s| s.to_str()),
+            Some("yml" | "yaml")
+        ) {
+            files.push(path);
+        }
+    }
+}
+
+/// Check `files` the way the timeline commands load them: standalone rules with
+/// `rule_from_yaml`, and files with a `correlation` document with `parse_rules_from_yaml`. Rule
+/// ids must be unique across all files, and correlations must only reference the base rules and
+/// correlations of correlation files (the only rules the correlation engine is given).
+pub fn check_rule_files(files: &[PathBuf]) -> Vec<RuleIssue> {
+    let mut issues = Vec::new();
+    let mut ids: HashMap<String, PathBuf> = HashMap::new();
+    let mut correlation_names = HashSet::new();
+    let mut references = Vec::new();
+    for path in files {
+        let mut issue = |message: String| {
+            issues.push(RuleIssue {
+                path: path.clone(),
+                message,
+            })
+        };
+        if path.extension().and_then(|s| s.to_str()) != Some("yml") {
+            issue("not loaded: rule files must have the .yml extension".to_string());
+            continue;
+        }
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                issue(format!("cannot read the file: {e}"));
+                continue;
+            }
+        };
+        let docs = match yaml_serde::Deserializer::from_str(&contents)
+            .map(Value::deserialize)
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(docs) => docs,
+            Err(e) => {
+                issue(format!("YAML error: {e}"));
+                continue;
+            }
+        };
+        for id in docs.iter().filter_map(|doc| str_field(doc, "id")) {
+            match ids.get(id) {
+                Some(other) => issue(format!("duplicate id {id} (also in {})", other.display())),
+                None => {
+                    ids.insert(id.to_string(), path.clone());
+                }
+            }
+        }
+
+        if docs.iter().any(|doc| doc.get("correlation").is_some()) {
+            let base_rules = match parse_rules_from_yaml(&contents) {
+                Ok((_, base_rules)) => base_rules,
+                Err(e) => {
+                    issue(format!("Sigma parse error: {e}"));
+                    continue;
+                }
+            };
+            for (name, rule) in &base_rules {
+                if !LogSource::All.is_supported_rule(rule) {
+                    issue(format!(
+                        "correlation base rule '{name}' has an unsupported logsource, so the correlation is skipped"
+                    ));
+                }
+            }
+            for doc in &docs {
+                correlation_names.extend(
+                    ["id", "name"]
+                        .into_iter()
+                        .filter_map(|key| str_field(doc, key))
+                        .map(str::to_string),
+                );
+                if let Some(rules) = doc
+                    .get("correlation")
+                    .and_then(|c| c.get("rules"))
+                    .and_then(Value::as_sequence)
+                {
+                    references.extend(
+                        rules
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .map(|r| (path.clone(), r.to_string())),
+                    );
+                }
+            }
+            continue;
+        }
+
+        let rule = match rule_from_yaml(&contents) {
+            Ok(rule) => rule,
+            Err(e) => {
+                issue(format!("Sigma parse error: {e}"));
+                continue;
+            }
+        };
+        if !LogSource::All.is_supported_rule(&rule) {
+            issue(format!(
+                "unsupported logsource (product: {}, service: {})",
+                rule.logsource.product.as_deref().unwrap_or("-"),
+                rule.logsource.service.as_deref().unwrap_or("-")
+            ));
+        }
+        if rule.level.is_none() {
+            issue("missing level, so the rule is never loaded".to_string());
+        }
+    }
+    for (path, reference) in references {
+        if !correlation_names.contains(&reference) {
+            issues.push(RuleIssue {
+                path,
+                message: format!("correlation references unknown rule '{reference}'"),
+            });
+        }
+    }
+    issues
+}
+
+fn str_field<'a>(doc: &'a Value, key: &str) -> Option<&'a str> {
+    doc.get(key).and_then(Value::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const RULE: &str = r#"
+title: Console Login
+id: 11111111-1111-1111-1111-111111111111
+level: medium
+logsource:
+  product: aws
+  service: cloudtrail
+detection:
+  selection:
+    eventName: ConsoleLogin
+  condition: selection
+"#;
+
+    fn messages(dir: &TempDir, name: &str) -> Vec<String> {
+        let files = rule_files(dir.path());
+        check_rule_files(&files)
+            .into_iter()
+            .filter(|issue| issue.path.ends_with(name))
+            .map(|issue| issue.message)
+            .collect()
+    }
+
+    #[test]
+    fn reports_each_kind_of_broken_rule() {
+        let dir = TempDir::new().unwrap();
+        let write =
+            |name: &str, contents: &str| fs::write(dir.path().join(name), contents).unwrap();
+        write("a_ok.yml", RULE);
+        write("b_dup.yml", RULE);
+        write("c_yaml.yml", "title: [unclosed\n");
+        write("d_sigma.yml", "title: No detection\nlevel: low\n");
+        write(
+            "e_service.yml",
+            &RULE
+                .replace("1111-1111-1111-1111", "2222-2222-2222-2222")
+                .replace("cloudtrail", "no-such-service"),
+        );
+        write(
+            "f_level.yml",
+            &RULE
+                .replace("1111-1111-1111-1111", "3333-3333-3333-3333")
+                .replace("level: medium\n", ""),
+        );
+        write("g_ext.yaml", RULE);
+
+        assert!(messages(&dir, "a_ok.yml").is_empty());
+        assert_eq!(messages(&dir, "b_dup.yml").len(), 1);
+        assert!(messages(&dir, "b_dup.yml")[0].starts_with("duplicate id"));
+        assert!(messages(&dir, "c_yaml.yml")[0].starts_with("YAML error"));
+        assert!(messages(&dir, "d_sigma.yml")[0].starts_with("Sigma parse error"));
+        assert!(messages(&dir, "e_service.yml")[0].contains("no-such-service"));
+        assert!(messages(&dir, "f_level.yml")[0].starts_with("missing level"));
+        assert!(messages(&dir, "g_ext.yaml")[0].contains(".yml extension"));
+    }
+
+    #[test]
+    fn reports_broken_correlation_references() {
+        let dir = TempDir::new().unwrap();
+        let base = RULE.replace(
+            "title: Console Login",
+            "title: Console Login\nname: console_login",
+        );
+        let correlation = |id: &str, rule: &str| {
+            format!(
+                "{base}---\ntitle: Many logins\nid: {id}\ncorrelation:\n  type: event_count\n  rules:\n    - {rule}\n  group-by:\n    - sourceIPAddress\n  timespan: 5m\n  condition:\n    gte: 3\nlevel: high\n"
+            )
+        };
+        fs::write(
+            dir.path().join("ok.yml"),
+            correlation("44444444-4444-4444-4444-444444444444", "console_login"),
+        )
+        .unwrap();
+        let ok = messages(&dir, "ok.yml");
+        assert!(ok.is_empty(), "{ok:?}");
+
+        fs::remove_file(dir.path().join("ok.yml")).unwrap();
+        fs::write(
+            dir.path().join("broken.yml"),
+            correlation("55555555-5555-5555-5555-555555555555", "no_such_rule"),
+        )
+        .unwrap();
+        assert!(!messages(&dir, "broken.yml").is_empty());
+    }
+}
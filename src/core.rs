@@ -1,7 +1,18 @@
+pub mod archive;
+pub mod azure_blob;
 pub mod color;
+pub mod dedup;
+pub mod flow_log;
+pub mod json_stream;
 pub(crate) mod log_source;
+pub mod manifest;
+pub mod object_store;
 pub mod rules;
+pub mod s3;
+pub mod s3_access_log;
 pub mod scan;
+pub mod scan_state;
+pub mod security_lake;
 pub mod summary;
 pub mod timeline;
 pub mod timeline_writer;
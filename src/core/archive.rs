@@ -0,0 +1,355 @@
+use crate::core::manifest;
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek};
+use std::path::Path;
+
+/// How deep archives may be nested (e.g. a `.zip` inside a `.tar.gz` inside a `.zip`) before the
+/// inner ones are skipped. Stops self-referencing "zip quine" archives from recursing forever.
+const MAX_NESTING_DEPTH: usize = 8;
+
+/// True if `path` is an archive or zstd-compressed file that is scanned with `visit_archive`
+/// rather than read as a single log file. Plain `.gz` files are read as a single log file.
+pub fn is_archive(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    [
+        ".zip", ".tar", ".tar.gz", ".tgz", ".tar.zst", ".tzst", ".zst", ".zstd",
+    ]
+    .iter()
+    .any(|ext| lower.ends_with(ext))
+}
+
+/// Decompressed bytes left before an archive is treated as a decompression bomb. Shared by every
+/// member (and nested archive) of one top-level archive, so many small members cannot add up to
+/// more than the limit either.
+struct Budget {
+    max_bytes: u64,
+    remaining: u64,
+    exceeded: bool,
+}
+
+impl Budget {
+    fn exceeded_error(&mut self) -> io::Error {
+        self.exceeded = true;
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "decompressed size exceeds the {} GiB limit (possible archive bomb)",
+                self.max_bytes / (1024 * 1024 * 1024)
+            ),
+        )
+    }
+
+    /// Read `reader` to the end, refusing to buffer more than the remaining budget.
+    fn read(&mut self, reader: &mut dyn Read) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        reader.take(self.remaining + 1).read_to_end(&mut buf)?;
+        if buf.len() as u64 > self.remaining {
+            return Err(self.exceeded_error());
+        }
+        self.remaining -= buf.len() as u64;
+        Ok(buf)
+    }
+}
+
+/// A member stream that counts what is read from it against the budget.
+struct Budgeted<'a> {
+    budget: &'a mut Budget,
+    inner: &'a mut dyn Read,
+}
+
+impl Read for Budgeted<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n as u64 > self.budget.remaining {
+            return Err(self.budget.exceeded_error());
+        }
+        self.budget.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Walks the members of one top-level archive.
+struct Visitor<'a, F: FnMut(&str, &mut dyn BufRead) -> io::Result<()>> {
+    budget: Budget,
+    is_log_member: &'a dyn Fn(&str) -> bool,
+    visit: F,
+}
+
+impl<F: FnMut(&str, &mut dyn BufRead) -> io::Result<()>> Visitor<'_, F> {
+    /// Dispatch one stream on its name: unpack archives and compressed files, and pass log files
+    /// to `visit` with their full path (`outer.zip/inner.tar.gz/member.json`).
+    fn visit_reader(
+        &mut self,
+        name: &str,
+        path: &str,
+        reader: &mut dyn Read,
+        depth: usize,
+    ) -> io::Result<()> {
+        let lower = name.to_ascii_lowercase();
+        let is_archive =
+            is_archive(&lower) && !lower.ends_with(".zst") && !lower.ends_with(".zstd");
+        if is_archive && depth >= MAX_NESTING_DEPTH {
+            manifest::warn_skipped(path, "archives are nested too deeply");
+            return Ok(());
+        }
+        if lower.ends_with(".zip") {
+            // The zip central directory is at the end of the file, so the member must be seekable.
+            let bytes = self.budget.read(reader)?;
+            self.visit_zip(Cursor::new(bytes), path, depth + 1)
+        } else if lower.ends_with(".tar") {
+            self.visit_tar(reader, path, depth + 1)
+        } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            self.visit_tar(&mut GzDecoder::new(reader), path, depth + 1)
+        } else if lower.ends_with(".tar.zst") || lower.ends_with(".tzst") {
+            self.visit_tar(&mut zstd::Decoder::new(reader)?, path, depth + 1)
+        } else if let Some(inner) = lower.strip_suffix(".gz") {
+            self.visit_reader(inner, path, &mut GzDecoder::new(reader), depth)
+        } else if let Some(inner) = lower
+            .strip_suffix(".zst")
+            .or_else(|| lower.strip_suffix(".zstd"))
+        {
+            self.visit_reader(inner, path, &mut zstd::Decoder::new(reader)?, depth)
+        } else if (self.is_log_member)(&lower) {
+            let mut member = BufReader::new(Budgeted {
+                budget: &mut self.budget,
+                inner: reader,
+            });
+            match (self.visit)(path, &mut member) {
+                Err(e) if self.budget.exceeded => Err(e),
+                Err(e) => {
+                    manifest::warn_skipped(path, &e.to_string());
+                    Ok(())
+                }
+                Ok(()) => Ok(()),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Visit one member of an archive. A member that cannot be read is skipped with a warning, so
+    /// one corrupt member does not lose the rest of the archive; only going over the budget stops
+    /// reading it.
+    fn visit_member(
+        &mut self,
+        name: &str,
+        path: &str,
+        reader: &mut dyn Read,
+        depth: usize,
+    ) -> io::Result<()> {
+        match self.visit_reader(name, path, reader, depth) {
+            Err(e) if self.budget.exceeded => Err(e),
+            Err(e) => {
+                manifest::warn_skipped(path, &e.to_string());
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+
+    fn visit_zip<R: Read + Seek>(&mut self, reader: R, path: &str, depth: usize) -> io::Result<()> {
+        let mut zip = zip::ZipArchive::new(reader).map_err(io::Error::other)?;
+        for i in 0..zip.len() {
+            let member_path = match zip.name_for_index(i) {
+                Some(name) => format!("{path}/{name}"),
+                None => format!("{path}/#{i}"),
+            };
+            let mut member = match zip.by_index(i) {
+                Ok(member) => member,
+                Err(e) => {
+                    manifest::warn_skipped(&member_path, &e.to_string());
+                    continue;
+                }
+            };
+            if member.is_dir() {
+                continue;
+            }
+            let name = member.name().to_string();
+            self.visit_member(&name, &member_path, &mut member, depth)?;
+        }
+        Ok(())
+    }
+
+    /// Unlike zip, a tar archive has no index, so an error in the stream itself (as opposed to in
+    /// one member's contents) stops reading the rest of the archive.
+    fn visit_tar(&mut self, reader: &mut dyn Read, path: &str, depth: usize) -> io::Result<()> {
+        let mut tar = tar::Archive::new(reader);
+        for member in tar.entries()? {
+            let mut member = member?;
+            if !member.header().entry_type().is_file() {
+                continue;
+            }
+            let name = match member.path() {
+                Ok(name) => name.to_string_lossy().to_string(),
+                Err(e) => {
+                    manifest::warn_skipped(&format!("{path}/?"), &e.to_string());
+                    continue;
+                }
+            };
+            self.visit_member(&name, &format!("{path}/{name}"), &mut member, depth)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the log files inside the archive at `path` (`.zip`, `.tar`, `.tar.gz`/`.tgz`,
+/// `.tar.zst`/`.tzst`, or a single `.zst` file), descending into nested archives and compressed
+/// members. `visit` is called with each member's path (`archive.zip/dir/member.json`) and a reader
+/// over its decompressed contents, for the members whose name `is_log_member` accepts; an error
+/// from `visit` skips that member with a warning. At most `max_bytes` are decompressed in total;
+/// going over the limit stops reading the archive with an error.
+pub fn visit_archive<F: FnMut(&str, &mut dyn BufRead) -> io::Result<()>>(
+    path: &Path,
+    max_bytes: u64,
+    is_log_member: &dyn Fn(&str) -> bool,
+    visit: F,
+) -> io::Result<()> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut visitor = Visitor {
+        budget: Budget {
+            max_bytes,
+            remaining: max_bytes,
+            exceeded: false,
+        },
+        is_log_member,
+        visit,
+    };
+    let path_str = path.to_string_lossy();
+    if path_str.to_ascii_lowercase().ends_with(".zip") {
+        // Read the file in place instead of buffering it.
+        return visitor.visit_zip(file, &path_str, 1);
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    visitor.visit_reader(&name, &path_str, &mut file, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn is_json(name: &str) -> bool {
+        name.ends_with(".json") || name.ends_with(".jsonl")
+    }
+
+    fn tar_gz(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tar = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (name, data) in members {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, *data).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn zip(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in members {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn collect(path: &Path, max_bytes: u64) -> io::Result<Vec<(String, String)>> {
+        let mut members = Vec::new();
+        visit_archive(path, max_bytes, &is_json, |p, reader| {
+            let mut contents = String::new();
+            reader.read_to_string(&mut contents)?;
+            members.push((p.to_string(), contents));
+            Ok(())
+        })?;
+        Ok(members)
+    }
+
+    #[test]
+    fn reads_nested_archives_and_reports_member_paths() {
+        let inner = tar_gz(&[
+            ("logs/a.json", br#"{"eventName":"A"}"#),
+            ("logs/readme.md", b"not a log"),
+        ]);
+        let gz = {
+            let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+            enc.write_all(br#"{"eventName":"B"}"#).unwrap();
+            enc.finish().unwrap()
+        };
+        let outer = zip(&[("inner.tar.gz", &inner), ("b.json.gz", &gz)]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.zip");
+        std::fs::write(&path, outer).unwrap();
+
+        let members = collect(&path, 1024 * 1024).unwrap();
+        let root = path.to_string_lossy();
+        assert_eq!(
+            members,
+            vec![
+                (
+                    format!("{root}/inner.tar.gz/logs/a.json"),
+                    r#"{"eventName":"A"}"#.to_string()
+                ),
+                (
+                    format!("{root}/b.json.gz"),
+                    r#"{"eventName":"B"}"#.to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_zstd_compressed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl.zst");
+        let data = zstd::encode_all(&b"{\"eventName\":\"A\"}\n"[..], 0).unwrap();
+        std::fs::write(&path, data).unwrap();
+        let members = collect(&path, 1024).unwrap();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].1, "{\"eventName\":\"A\"}\n");
+        assert!(is_archive("events.jsonl.zst"));
+        assert!(!is_archive("events.json.gz"));
+    }
+
+    #[test]
+    fn skips_corrupt_members_and_reads_the_rest() {
+        let good: &[u8] = br#"{"eventName":"A"}"#;
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("export.zip");
+        std::fs::write(
+            &zip_path,
+            zip(&[("bad.zip", b"not a zip"), ("a.json", good)]),
+        )
+        .unwrap();
+        let tgz_path = dir.path().join("bundle.tgz");
+        std::fs::write(
+            &tgz_path,
+            tar_gz(&[("bad.tar.gz", b"not gzip"), ("a.json", good)]),
+        )
+        .unwrap();
+
+        for path in [zip_path, tgz_path] {
+            let members = collect(&path, 1024 * 1024).unwrap();
+            assert_eq!(members.len(), 1, "{}", path.display());
+            assert!(members[0].0.ends_with("/a.json"));
+        }
+    }
+
+    #[test]
+    fn budget_is_shared_across_members() {
+        let member = vec![b' '; 600];
+        let outer = tar_gz(&[("a.json", &member), ("b.json", &member)]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.tgz");
+        std::fs::write(&path, outer).unwrap();
+        assert!(collect(&path, 1200).is_ok());
+        let err = collect(&path, 1000).unwrap_err();
+        assert!(err.to_string().contains("possible archive bomb"), "{err}");
+    }
+}
@@ -0,0 +1,371 @@
+use crate::core::object_store::{
+    Listing, ObjectStore, RemoteObject, error_message, http_agent, send_with_retries, uri_encode,
+    xml_unescape,
+};
+use crate::option::cli::AzureBlobOption;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use sha2::Sha256;
+use std::io::{self, Read};
+use std::sync::LazyLock;
+
+/// Blob service REST API version sent with every request (also supported by Azurite).
+const API_VERSION: &str = "2021-08-06";
+
+static BLOB_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?s)<Blob>.*?<Name>(.*?)</Name>.*?<Content-Length>(\d+)</Content-Length>.*?</Blob>",
+    )
+    .expect("BLOB_RE regex pattern is invalid")
+});
+static BLOB_PREFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)<BlobPrefix>\s*<Name>(.*?)</Name>")
+        .expect("BLOB_PREFIX_RE regex pattern is invalid")
+});
+static NEXT_MARKER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<NextMarker>(.+?)</NextMarker>").expect("NEXT_MARKER_RE regex pattern is invalid")
+});
+
+/// The parts of a container URL: `https://account.blob.core.windows.net/container/prefix`, or
+/// the path-style `http://127.0.0.1:10000/account/container/prefix` Azurite and other custom
+/// endpoints use. A SAS token may be appended as the query string.
+#[derive(Debug, PartialEq)]
+pub struct ContainerUrl {
+    /// `scheme://host[:port]`
+    pub origin: String,
+    pub account: String,
+    /// URI-encoded path of the container on `origin` (`/container` or `/account/container`).
+    pub container_path: String,
+    pub prefix: String,
+    pub sas: Option<String>,
+}
+
+pub fn parse_container_url(url: &str) -> Result<ContainerUrl, String> {
+    let invalid = || {
+        format!(
+            "'{url}' is not an Azure Blob Storage container URL (e.g. https://account.blob.core.windows.net/insights-logs-signinlogs/)"
+        )
+    };
+    let (url, sas) = match url.split_once('?') {
+        Some((url, sas)) if !sas.is_empty() => (url, Some(sas.to_string())),
+        Some((url, _)) => (url, None),
+        None => (url, None),
+    };
+    let (scheme, rest) = url.split_once("://").ok_or_else(invalid)?;
+    if scheme != "https" && scheme != "http" {
+        return Err(invalid());
+    }
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let hostname = host.split(':').next().unwrap_or_default();
+    let (account, path) = match hostname.split_once(".blob.") {
+        Some((account, _)) => (account, path),
+        // Path style: the account is the first path segment.
+        None => path.split_once('/').unwrap_or((path, "")),
+    };
+    let (container, prefix) = path.split_once('/').unwrap_or((path, ""));
+    if account.is_empty() || container.is_empty() {
+        return Err(invalid());
+    }
+    let container_path = if hostname.contains(".blob.") {
+        format!("/{container}")
+    } else {
+        format!("/{account}/{container}")
+    };
+    Ok(ContainerUrl {
+        origin: format!("{scheme}://{host}"),
+        account: account.to_string(),
+        container_path,
+        prefix: percent_decode(prefix),
+        sas,
+    })
+}
+
+/// Decode `%XX` escapes, so a prefix copied from an encoded URL matches the blob names.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = s
+            .get(i + 1..i + 3)
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+enum Auth {
+    Anonymous,
+    Sas(String),
+    SharedKey(Vec<u8>),
+}
+
+/// A container in Azure Blob Storage (or Azurite), read with List Blobs and Get Blob. Requests
+/// are authorized with a SAS token or the account's shared key, or sent anonymously (for
+/// containers with public read access).
+pub struct AzureBlobStore {
+    agent: ureq::Agent,
+    origin: String,
+    account: String,
+    container_path: String,
+    auth: Auth,
+}
+
+impl AzureBlobStore {
+    pub fn new(container: &ContainerUrl, opt: &AzureBlobOption) -> Result<Self, String> {
+        let sas = opt.sas_token.as_deref().map(|s| s.trim_start_matches('?'));
+        let auth = match (sas.or(container.sas.as_deref()), &opt.account_key) {
+            (Some(_), Some(_)) => {
+                return Err("Specify either a SAS token or an account key, not both.".to_string());
+            }
+            (Some(sas), None) => Auth::Sas(sas.to_string()),
+            (None, Some(key)) => Auth::SharedKey(
+                BASE64
+                    .decode(key.trim())
+                    .map_err(|e| format!("The account key is not valid base64: {e}"))?,
+            ),
+            (None, None) => Auth::Anonymous,
+        };
+        Ok(AzureBlobStore {
+            agent: http_agent(),
+            origin: container.origin.clone(),
+            account: container.account.clone(),
+            container_path: container.container_path.clone(),
+            auth,
+        })
+    }
+
+    /// Send a GET for the blob `name` (or the container itself if `None`) with `query`, and
+    /// return the response if it succeeded.
+    fn get_response(
+        &self,
+        name: Option<&str>,
+        query: &[(&str, &str)],
+    ) -> io::Result<ureq::http::Response<ureq::Body>> {
+        let path = match name {
+            Some(name) => format!("{}/{}", self.container_path, uri_encode(name, false)),
+            None => self.container_path.clone(),
+        };
+        let mut params: Vec<String> = query
+            .iter()
+            .map(|(k, v)| format!("{k}={}", uri_encode(v, true)))
+            .collect();
+        if let Auth::Sas(sas) = &self.auth {
+            params.push(sas.clone());
+        }
+        let url = if params.is_empty() {
+            format!("{}{path}", self.origin)
+        } else {
+            format!("{}{path}?{}", self.origin, params.join("&"))
+        };
+        let mut response = send_with_retries(|| {
+            let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+            let mut request = self
+                .agent
+                .get(&url)
+                .header("x-ms-date", &date)
+                .header("x-ms-version", API_VERSION);
+            if let Auth::SharedKey(key) = &self.auth {
+                let headers = [("x-ms-date", date.as_str()), ("x-ms-version", API_VERSION)];
+                let signature = shared_key_signature(key, &self.account, &path, query, &headers);
+                request = request.header(
+                    "authorization",
+                    format!("SharedKey {}:{signature}", self.account),
+                );
+            }
+            request.call()
+        })
+        .map_err(io::Error::other)?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        Err(io::Error::other(error_message(&mut response)))
+    }
+}
+
+impl ObjectStore for AzureBlobStore {
+    fn url(&self, key: &str) -> String {
+        format!("{}{}/{key}", self.origin, self.container_path)
+    }
+
+    fn list(&self, prefix: &str, delimited: bool) -> io::Result<Listing> {
+        let mut listing = Listing::default();
+        let mut marker: Option<String> = None;
+        loop {
+            let mut query = vec![("restype", "container"), ("comp", "list")];
+            if !prefix.is_empty() {
+                query.push(("prefix", prefix));
+            }
+            if delimited {
+                query.push(("delimiter", "/"));
+            }
+            if let Some(marker) = &marker {
+                query.push(("marker", marker.as_str()));
+            }
+            let body = self
+                .get_response(None, &query)?
+                .body_mut()
+                .read_to_string()
+                .map_err(io::Error::other)?;
+            marker = parse_list_page(&body, &mut listing);
+            if marker.is_none() {
+                return Ok(listing);
+            }
+        }
+    }
+
+    fn get(&self, key: &str) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(
+            self.get_response(Some(key), &[])?.into_body().into_reader(),
+        ))
+    }
+}
+
+/// Add one List Blobs result page to `listing`, and return the marker for the next page if
+/// there is one.
+fn parse_list_page(body: &str, listing: &mut Listing) -> Option<String> {
+    for caps in BLOB_RE.captures_iter(body) {
+        listing.objects.push(RemoteObject {
+            key: xml_unescape(&caps[1]),
+            size: caps[2].parse().unwrap_or(0),
+        });
+    }
+    for caps in BLOB_PREFIX_RE.captures_iter(body) {
+        listing.prefixes.push(xml_unescape(&caps[1]));
+    }
+    NEXT_MARKER_RE
+        .captures(body)
+        .map(|caps| xml_unescape(&caps[1]))
+}
+
+/// The Shared Key signature of a bodiless GET of `path` (URI-encoded) with `query` (not
+/// encoded) and the `x-ms-*` `headers`.
+fn shared_key_signature(
+    key: &[u8],
+    account: &str,
+    path: &str,
+    query: &[(&str, &str)],
+    headers: &[(&str, &str)],
+) -> String {
+    let mut headers = headers.to_vec();
+    headers.sort();
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    let mut query: Vec<(String, &str)> = query
+        .iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), *value))
+        .collect();
+    query.sort();
+    let canonical_query: String = query
+        .iter()
+        .map(|(name, value)| format!("\n{name}:{value}"))
+        .collect();
+    // VERB, then the eleven standard headers a bodiless GET leaves empty (Content-Encoding ...
+    // Range), then the `x-ms-*` headers and the resource.
+    let string_to_sign =
+        format!("GET\n\n\n\n\n\n\n\n\n\n\n\n{canonical_headers}/{account}{path}{canonical_query}");
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(string_to_sign.as_bytes());
+    BASE64.encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_container_urls() {
+        assert_eq!(
+            parse_container_url(
+                "https://acct.blob.core.windows.net/insights-logs-signinlogs/resourceId%3D/TENANTS/?sv=2022-11-02&sig=abc"
+            ),
+            Ok(ContainerUrl {
+                origin: "https://acct.blob.core.windows.net".to_string(),
+                account: "acct".to_string(),
+                container_path: "/insights-logs-signinlogs".to_string(),
+                prefix: "resourceId=/TENANTS/".to_string(),
+                sas: Some("sv=2022-11-02&sig=abc".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_container_url("http://127.0.0.1:10000/devstoreaccount1/logs"),
+            Ok(ContainerUrl {
+                origin: "http://127.0.0.1:10000".to_string(),
+                account: "devstoreaccount1".to_string(),
+                container_path: "/devstoreaccount1/logs".to_string(),
+                prefix: String::new(),
+                sas: None,
+            })
+        );
+        assert!(parse_container_url("https://acct.blob.core.windows.net/").is_err());
+        assert!(parse_container_url("s3://bucket/key").is_err());
+    }
+
+    #[test]
+    fn signs_requests_with_the_shared_key() {
+        // Azurite's well-known development account key.
+        let key = BASE64
+            .decode("Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==")
+            .unwrap();
+        let signature = shared_key_signature(
+            &key,
+            "devstoreaccount1",
+            "/devstoreaccount1/logs",
+            &[
+                ("restype", "container"),
+                ("comp", "list"),
+                ("prefix", "y=2024/"),
+            ],
+            &[
+                ("x-ms-version", API_VERSION),
+                ("x-ms-date", "Mon, 15 Jan 2024 03:00:00 GMT"),
+            ],
+        );
+        // HMAC-SHA256 of the string to sign, computed independently (Python's `hmac`):
+        // "GET" + 12 "\n" + "x-ms-date:Mon, 15 Jan 2024 03:00:00 GMT\nx-ms-version:2021-08-06\n"
+        // + "/devstoreaccount1/devstoreaccount1/logs\ncomp:list\nprefix:y=2024/\nrestype:container"
+        assert_eq!(signature, "UHmFqBqwLAvcSlpVqiEEg6NPDFuDyBmx+TWZJ+DRDUM=");
+    }
+
+    #[test]
+    fn parses_list_blobs_pages() {
+        let page = r#"<?xml version="1.0" encoding="utf-8"?>
+<EnumerationResults ServiceEndpoint="https://acct.blob.core.windows.net/" ContainerName="insights-logs-auditlogs">
+  <Prefix>resourceId=/</Prefix><Delimiter>/</Delimiter>
+  <Blobs>
+    <Blob><Name>y=2024/m=01/d=15/h=03/m=00/PT1H.json</Name><Properties><Last-Modified>Mon, 15 Jan 2024 04:00:00 GMT</Last-Modified><Content-Length>2048</Content-Length><BlobType>AppendBlob</BlobType></Properties></Blob>
+    <BlobPrefix><Name>resourceId=/TENANTS/</Name></BlobPrefix>
+  </Blobs>
+  <NextMarker>2!96!MDAwMDM2</NextMarker>
+</EnumerationResults>"#;
+        let mut listing = Listing::default();
+        assert_eq!(
+            parse_list_page(page, &mut listing).as_deref(),
+            Some("2!96!MDAwMDM2")
+        );
+        assert_eq!(
+            listing.objects,
+            vec![RemoteObject {
+                key: "y=2024/m=01/d=15/h=03/m=00/PT1H.json".to_string(),
+                size: 2048
+            }]
+        );
+        assert_eq!(listing.prefixes, vec!["resourceId=/TENANTS/"]);
+        let last = page.replace("<NextMarker>2!96!MDAwMDM2</NextMarker>", "<NextMarker />");
+        assert_eq!(parse_list_page(&last, &mut Listing::default()), None);
+    }
+}
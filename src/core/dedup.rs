@@ -0,0 +1,146 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashSet, VecDeque};
+
+/// Event keys `--dedup` remembers. Once full, the oldest keys are forgotten, so memory stays
+/// bounded (roughly 50 MB per million keys) and only copies further apart than this many events
+/// are kept.
+const MAX_SEEN_EVENTS: usize = 5_000_000;
+
+/// The keys of the events scanned so far, for dropping the copies overlapping exports contain
+/// (e.g. an organization trail and an account trail, or Event history and the S3 trail).
+#[derive(Debug)]
+pub struct SeenEvents {
+    /// The first 128 bits of the SHA-256 of each key, so distinct events never collide in
+    /// practice while memory use stays a fraction of keeping the keys themselves.
+    seen: HashSet<u128>,
+    order: VecDeque<u128>,
+    capacity: usize,
+}
+
+impl Default for SeenEvents {
+    fn default() -> Self {
+        SeenEvents::with_capacity(MAX_SEEN_EVENTS)
+    }
+}
+
+impl SeenEvents {
+    fn with_capacity(capacity: usize) -> Self {
+        SeenEvents {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// True if an event with the same key was already seen. Events without a key are never
+    /// treated as duplicates.
+    pub fn is_duplicate(&mut self, event: &Value) -> bool {
+        let Some(key) = event_key(event) else {
+            return false;
+        };
+        let digest = Sha256::digest(key.as_bytes());
+        let hash = u128::from_be_bytes(digest[..16].try_into().unwrap());
+        if !self.seen.insert(hash) {
+            return true;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.seen.remove(&oldest);
+        }
+        false
+    }
+}
+
+/// What identifies a record across exports: the CloudTrail `eventID`, or for Azure records the
+/// record `id` (`Id` in the Microsoft 365 audit log), falling back to `correlationId` and `time`.
+fn event_key(event: &Value) -> Option<String> {
+    if let Some(id) = event.get("eventID").and_then(Value::as_str) {
+        return Some(format!("aws:{id}"));
+    }
+    if !is_azure_record(event) {
+        return None;
+    }
+    if let Some(id) = ["id", "Id"]
+        .iter()
+        .find_map(|k| event.get(*k).and_then(Value::as_str))
+    {
+        return Some(format!("azure:{id}"));
+    }
+    let correlation_id = event.get("correlationId").and_then(Value::as_str)?;
+    let time = event.get("time").and_then(Value::as_str)?;
+    Some(format!("azure:{correlation_id}|{time}"))
+}
+
+/// True for the records the Azure rules are routed to (see `is_match_service`): Azure Monitor
+/// logs (activity, Entra ID audit and sign-in) with their `category`, Microsoft 365 audit log
+/// records, and Entra ID risk events. Other sources' `id` fields do not identify a record the
+/// same way, so they are never keyed by it.
+fn is_azure_record(event: &Value) -> bool {
+    (event.get("category").is_some() && event.get("operationName").is_some())
+        || ["Workload", "RecordType", "riskEventType"]
+            .iter()
+            .any(|k| event.get(*k).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn drops_repeated_event_ids() {
+        let mut seen = SeenEvents::default();
+        let event = json!({"eventID": "a1", "eventName": "ConsoleLogin"});
+        assert!(!seen.is_duplicate(&event));
+        assert!(seen.is_duplicate(&event));
+        assert!(!seen.is_duplicate(&json!({"eventID": "a2"})));
+        // Records without a key are all kept.
+        assert!(!seen.is_duplicate(&json!({"eventName": "x"})));
+        assert!(!seen.is_duplicate(&json!({"eventName": "x"})));
+    }
+
+    #[test]
+    fn keys_azure_records_by_id_or_correlation_id_and_time() {
+        let mut seen = SeenEvents::default();
+        let activity = |fields: Value| {
+            let mut record = json!({"category": "Administrative", "operationName": "Delete"});
+            record
+                .as_object_mut()
+                .unwrap()
+                .extend(fields.as_object().unwrap().clone());
+            record
+        };
+        assert!(!seen.is_duplicate(&activity(json!({"id": "/subscriptions/s/events/e1"}))));
+        assert!(seen.is_duplicate(&activity(json!({"id": "/subscriptions/s/events/e1"}))));
+        assert!(!seen.is_duplicate(&json!({"Id": "u1", "Workload": "Exchange"})));
+        assert!(seen.is_duplicate(&json!({"Id": "u1", "Workload": "Exchange"})));
+        let record = activity(json!({"correlationId": "c1", "time": "2024-01-15T03:00:00Z"}));
+        assert!(!seen.is_duplicate(&record));
+        assert!(seen.is_duplicate(&record));
+        assert!(!seen.is_duplicate(&activity(
+            json!({"correlationId": "c1", "time": "2024-01-15T03:00:01Z"})
+        )));
+    }
+
+    #[test]
+    fn other_sources_are_not_keyed_by_azure_fields() {
+        let mut seen = SeenEvents::default();
+        let record = json!({"id": "1", "correlationId": "c1", "time": "2024-01-15T03:00:00Z"});
+        assert!(!seen.is_duplicate(&record));
+        assert!(!seen.is_duplicate(&record));
+    }
+
+    #[test]
+    fn forgets_the_oldest_keys_when_full() {
+        let mut seen = SeenEvents::with_capacity(2);
+        for id in ["a", "b", "c"] {
+            assert!(!seen.is_duplicate(&json!({ "eventID": id })));
+        }
+        assert!(seen.is_duplicate(&json!({"eventID": "c"})));
+        assert!(!seen.is_duplicate(&json!({"eventID": "a"})));
+        assert_eq!(seen.seen.len(), 2);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::core::json_stream;
+use chrono::{DateTime, SecondsFormat};
+use serde_json::{Map, Value};
+use std::io::{self, BufRead};
+
+/// Field order of the default (version 2) VPC Flow Logs format. Used when a file has no header
+/// line, as with flow logs published to CloudWatch Logs.
+const DEFAULT_V2_FIELDS: [&str; 14] = [
+    "version",
+    "account-id",
+    "interface-id",
+    "srcaddr",
+    "dstaddr",
+    "srcport",
+    "dstport",
+    "protocol",
+    "packets",
+    "bytes",
+    "start",
+    "end",
+    "action",
+    "log-status",
+];
+
+/// Fields kept as JSON numbers so they can be summed and compared numerically.
+const NUMERIC_FIELDS: [&str; 11] = [
+    "version",
+    "srcport",
+    "dstport",
+    "protocol",
+    "packets",
+    "bytes",
+    "start",
+    "end",
+    "tcp-flags",
+    "traffic-path",
+    "pkt-src-aws-service-port",
+];
+
+/// True if `line` is a flow log header: the space-separated field names of a default or custom
+/// format (e.g. `version account-id interface-id srcaddr ...`), as written at the top of every
+/// file delivered to S3.
+fn is_header(line: &str) -> bool {
+    let mut fields = line.split_whitespace().peekable();
+    fields.peek().is_some()
+        && line.contains("srcaddr")
+        && fields.all(|f| {
+            f.starts_with(|c: char| c.is_ascii_lowercase())
+                && f.chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+}
+
+/// True if `contents` looks like a VPC Flow Logs file: a header line, or a first record in the
+/// default version 2 format.
+pub fn is_flow_log(contents: &str) -> bool {
+    let Some(first) = contents.lines().find(|l| !l.trim().is_empty()) else {
+        return false;
+    };
+    if is_header(first) {
+        return true;
+    }
+    let fields: Vec<&str> = first.split_whitespace().collect();
+    fields.len() == DEFAULT_V2_FIELDS.len() && fields[0] == "2" && fields[2].starts_with("eni-")
+}
+
+/// Parse one space-delimited record into a JSON object keyed by `fields`. `-` (no data, e.g.
+/// `NODATA`/`SKIPDATA` records) is left out. An `eventTime` (RFC 3339) derived from `start` is
+/// added so the records can be sorted and time-filtered like CloudTrail events.
+fn parse_record(fields: &[String], line: &str) -> Value {
+    let mut map = Map::new();
+    for (field, value) in fields.iter().zip(line.split_whitespace()) {
+        if value == "-" {
+            continue;
+        }
+        let value = match value.parse::<u64>() {
+            Ok(n) if NUMERIC_FIELDS.contains(&field.as_str()) => Value::from(n),
+            _ => Value::String(value.to_string()),
+        };
+        map.insert(field.clone(), value);
+    }
+    if let Some(start) = map.get("start").and_then(Value::as_i64)
+        && let Some(time) = DateTime::from_timestamp(start, 0)
+    {
+        map.insert(
+            "eventTime".to_string(),
+            Value::String(time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        );
+    }
+    Value::Object(map)
+}
+
+/// Stream the records of a VPC Flow Logs file to `emit`, one JSON object per record, keyed by the
+/// field names of the file's header line (so custom formats keep their own field order), or by
+/// the default version 2 fields when there is no header. A file that is not a flow log yields
+/// nothing.
+pub fn stream_flow_log(reader: &mut dyn BufRead, emit: &mut dyn FnMut(Value)) -> io::Result<()> {
+    let mut line = String::new();
+    let mut fields: Option<Vec<String>> = None;
+    while json_stream::read_line(reader, &mut line)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = match &fields {
+            Some(fields) => fields,
+            None if !is_flow_log(&line) => return Ok(()),
+            None => fields.insert(if is_header(&line) {
+                line.split_whitespace().map(str::to_string).collect()
+            } else {
+                DEFAULT_V2_FIELDS.iter().map(|f| f.to_string()).collect()
+            }),
+        };
+        // Header lines (the first, or repeated in concatenated files) are not records.
+        if !is_header(&line) {
+            emit(parse_record(fields, &line));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a whole VPC Flow Logs file with `stream_flow_log`.
+    fn parse_flow_log(contents: &str) -> Vec<Value> {
+        let mut events = Vec::new();
+        // Reading from a string cannot fail.
+        stream_flow_log(&mut contents.as_bytes(), &mut |v| events.push(v)).ok();
+        events
+    }
+
+    #[test]
+    fn parses_default_format_with_header() {
+        let contents = "version account-id interface-id srcaddr dstaddr srcport dstport protocol packets bytes start end action log-status\n\
+            2 123456789012 eni-0a1b2c3d 203.0.113.12 10.0.1.5 49152 22 6 20 4249 1705287845 1705287905 REJECT OK\n";
+        let events = parse_flow_log(contents);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["srcaddr"], "203.0.113.12");
+        assert_eq!(events[0]["dstport"], 22);
+        assert_eq!(events[0]["bytes"], 4249);
+        assert_eq!(events[0]["action"], "REJECT");
+        assert_eq!(events[0]["eventTime"], "2024-01-15T03:04:05Z");
+    }
+
+    #[test]
+    fn honors_custom_field_order() {
+        let contents = "start srcaddr dstaddr bytes pkt-srcaddr action\n\
+            1705287845 10.0.1.5 198.51.100.7 1048576 10.0.1.5 ACCEPT\n";
+        let events = parse_flow_log(contents);
+        assert_eq!(events[0]["dstaddr"], "198.51.100.7");
+        assert_eq!(events[0]["bytes"], 1048576);
+        assert_eq!(events[0]["pkt-srcaddr"], "10.0.1.5");
+        assert!(events[0].get("version").is_none());
+    }
+
+    #[test]
+    fn headerless_records_use_default_fields_and_skip_no_data() {
+        let contents = "2 123456789012 eni-0a1b2c3d - - - - - - - 1705287845 1705287905 - NODATA\n";
+        assert!(is_flow_log(contents));
+        let events = parse_flow_log(contents);
+        assert_eq!(events[0]["log-status"], "NODATA");
+        assert!(events[0].get("srcaddr").is_none());
+        assert_eq!(events[0]["interface-id"], "eni-0a1b2c3d");
+    }
+
+    #[test]
+    fn json_is_not_a_flow_log() {
+        assert!(!is_flow_log(r#"{"Records":[]}"#));
+        assert!(!is_flow_log(""));
+    }
+}
@@ -0,0 +1,442 @@
+use serde::Deserialize;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::io::{self, BufRead, Read};
+
+/// Longest single JSONL line that is read. A line is held in memory while it is parsed, so this
+/// stops a corrupt file (or a decompression bomb with no newlines) from exhausting memory.
+const MAX_LINE_BYTES: u64 = 1024 * 1024 * 1024; // 1 GiB
+
+/// Strip the `<timestamp> ` prefix that a CloudWatch Logs export (`CreateExportTask`) puts in
+/// front of each event (CloudTrail or EKS audit), e.g. `2024-01-15T03:04:05.123Z {"kind":...}`.
+fn strip_cloudwatch_export_prefix(line: &str) -> &str {
+    match line.find('{') {
+        Some(i) if i > 0 && !line[..i].trim().contains(' ') => &line[i..],
+        _ => line,
+    }
+}
+
+/// Parse the JSON documents on one line of a JSONL-style export, after stripping a CloudWatch
+/// Logs export prefix. Several documents concatenated on one line (`{...}{...}`), as Firehose
+/// writes CloudWatch Logs subscription batches to S3, are all returned.
+fn parse_json_line(line: &str) -> Vec<Value> {
+    serde_json::Deserializer::from_str(strip_cloudwatch_export_prefix(line))
+        .into_iter::<Value>()
+        .map_while(Result::ok)
+        .collect()
+}
+
+/// Skip a UTF-8 byte order mark at the start of `reader`.
+pub fn skip_bom(reader: &mut dyn BufRead) -> io::Result<()> {
+    if reader.fill_buf()?.starts_with(b"\xEF\xBB\xBF") {
+        reader.consume(3);
+    }
+    Ok(())
+}
+
+/// Read one line of at most `MAX_LINE_BYTES` into `line`. Returns `false` at the end of input.
+/// Also used by the line-based text formats (VPC Flow Logs, S3 server access logs).
+pub fn read_line(reader: &mut dyn BufRead, line: &mut String) -> io::Result<bool> {
+    read_line_capped(reader, line, MAX_LINE_BYTES)
+}
+
+fn read_line_capped(
+    reader: &mut dyn BufRead,
+    line: &mut String,
+    max_bytes: u64,
+) -> io::Result<bool> {
+    line.clear();
+    let n = reader.take(max_bytes + 1).read_line(line)?;
+    if n as u64 > max_bytes {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "a line exceeds the {} GiB limit (possible decompression bomb)",
+                max_bytes / (1024 * 1024 * 1024)
+            ),
+        ));
+    }
+    Ok(n > 0)
+}
+
+/// Streams one top-level JSON document: every element of a top-level array, or of the array
+/// under one of `envelope_keys` in a top-level object (e.g. CloudTrail's `Records`), is emitted
+/// as soon as it is parsed. Any other document is emitted whole.
+struct Document<'a> {
+    envelope_keys: &'a [&'a str],
+    emit: &'a mut dyn FnMut(Value),
+}
+
+impl<'de> DeserializeSeed<'de> for Document<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Document<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON object or array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(element) = seq.next_element::<Value>()? {
+            (self.emit)(element);
+        }
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut rest = Map::new();
+        let mut streamed = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if self.envelope_keys.contains(&key.as_str()) {
+                let envelope = Envelope {
+                    emit: &mut *self.emit,
+                };
+                match map.next_value_seed(envelope)? {
+                    Some(value) => {
+                        rest.insert(key, value);
+                    }
+                    None => streamed = true,
+                }
+            } else {
+                rest.insert(key, map.next_value()?);
+            }
+        }
+        // The other fields of an envelope (e.g. `NextToken`) are not events.
+        if !streamed {
+            (self.emit)(Value::Object(rest));
+        }
+        Ok(())
+    }
+
+    // A bare scalar is not a log record.
+    fn visit_bool<E>(self, _: bool) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E> {
+        Ok(())
+    }
+}
+
+/// The value under an envelope key: an array is streamed element by element (returns `None`);
+/// anything else is returned as is.
+struct Envelope<'a> {
+    emit: &'a mut dyn FnMut(Value),
+}
+
+impl<'de> DeserializeSeed<'de> for Envelope<'_> {
+    type Value = Option<Value>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<Value>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Envelope<'_> {
+    type Value = Option<Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Option<Value>, A::Error> {
+        while let Some(element) = seq.next_element::<Value>()? {
+            (self.emit)(element);
+        }
+        Ok(None)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Option<Value>, A::Error> {
+        Value::deserialize(MapAccessDeserializer::new(map)).map(Some)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Option<Value>, E> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Option<Value>, E> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Option<Value>, E> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Option<Value>, E> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Option<Value>, E> {
+        Ok(Some(Value::from(v)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Option<Value>, E> {
+        Ok(Some(Value::Null))
+    }
+}
+
+/// Inputs up to this size are read into memory and parsed from a slice, which is faster than
+/// parsing from a reader. Larger inputs are parsed incrementally.
+const IN_MEMORY_BYTES: u64 = 64 * 1024 * 1024; // 64 MiB
+
+/// Parse consecutive JSON documents (a single document, JSONL, or concatenated documents) with
+/// the `Document` seed until the end of input.
+fn stream_documents<'de, R: serde_json::de::Read<'de>>(
+    de: &mut serde_json::Deserializer<R>,
+    envelope_keys: &[&str],
+    emit: &mut dyn FnMut(Value),
+) -> serde_json::Result<()> {
+    loop {
+        Document {
+            envelope_keys,
+            emit: &mut *emit,
+        }
+        .deserialize(&mut *de)?;
+        if de.end().is_ok() {
+            return Ok(());
+        }
+    }
+}
+
+/// Read `reader` line by line, emitting the JSON documents on each line the way
+/// `stream_documents` emits them (unwrapping `envelope_keys` arrays). Lines that are not JSON are
+/// skipped.
+fn stream_lines(
+    reader: &mut dyn BufRead,
+    envelope_keys: &[&str],
+    emit: &mut dyn FnMut(Value),
+) -> io::Result<()> {
+    let mut line = String::new();
+    while read_line(reader, &mut line)? {
+        let mut values = Vec::new();
+        let mut de = serde_json::Deserializer::from_str(strip_cloudwatch_export_prefix(&line));
+        if stream_documents(&mut de, envelope_keys, &mut |v| values.push(v)).is_err() {
+            values = parse_json_line(&line);
+        }
+        values.into_iter().for_each(&mut *emit);
+    }
+    Ok(())
+}
+
+/// True if the first non-blank line of `head` is a whole JSON document, i.e. the input is JSONL
+/// rather than one (pretty-printed or very long) document.
+fn starts_with_document_line(head: &[u8]) -> bool {
+    let Some(start) = head.iter().position(|b| !b.is_ascii_whitespace()) else {
+        return false;
+    };
+    let Some(len) = head[start..].iter().position(|b| *b == b'\n') else {
+        return false;
+    };
+    serde_json::Deserializer::from_slice(&head[start..start + len])
+        .into_iter::<IgnoredAny>()
+        .next()
+        .is_some_and(|doc| doc.is_ok())
+}
+
+/// Stream the JSON documents in `reader` to `emit` without reading a large input into memory as
+/// a whole.
+///
+/// The input is parsed as a sequence of JSON documents: the elements of a top-level array, or of
+/// the array under one of `envelope_keys` in a top-level object (e.g. CloudTrail's `Records`), are
+/// emitted one by one as they are parsed, and any other document is emitted whole. If that fails,
+/// the input is read as JSONL: one or more documents per line, optionally prefixed with a
+/// CloudWatch Logs export timestamp, skipping lines that are not JSON. The fallback is only
+/// possible for inputs small enough to be held in memory, so a large input whose first line is a
+/// whole document is read as JSONL from the start, and one malformed line only loses that line. A
+/// large input that is a single document and is not valid JSON stops with an error after emitting
+/// what was parsed.
+pub fn stream_json(
+    reader: &mut dyn BufRead,
+    envelope_keys: &[&str],
+    emit: &mut dyn FnMut(Value),
+) -> io::Result<()> {
+    stream_json_with_limit(reader, envelope_keys, emit, IN_MEMORY_BYTES)
+}
+
+fn stream_json_with_limit(
+    reader: &mut dyn BufRead,
+    envelope_keys: &[&str],
+    emit: &mut dyn FnMut(Value),
+    in_memory_bytes: u64,
+) -> io::Result<()> {
+    let mut head = Vec::new();
+    (&mut *reader)
+        .take(in_memory_bytes)
+        .read_to_end(&mut head)?;
+    let complete = (head.len() as u64) < in_memory_bytes;
+    let body = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&head);
+    let starts_document = body
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|b| *b == b'{' || *b == b'[');
+
+    if complete {
+        if starts_document {
+            let mut values = Vec::new();
+            let mut de = serde_json::Deserializer::from_slice(body);
+            if stream_documents(&mut de, envelope_keys, &mut |v| values.push(v)).is_ok() {
+                values.into_iter().for_each(emit);
+                return Ok(());
+            }
+        }
+        return stream_lines(&mut &body[..], envelope_keys, emit);
+    }
+    let mut rest = body.chain(reader);
+    if starts_document && !starts_with_document_line(body) {
+        let mut de = serde_json::Deserializer::from_reader(rest);
+        stream_documents(&mut de, envelope_keys, emit).map_err(io::Error::from)
+    } else {
+        stream_lines(&mut rest, envelope_keys, emit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(input: &str, envelope_keys: &[&str]) -> io::Result<Vec<Value>> {
+        let mut values = Vec::new();
+        stream_json(&mut input.as_bytes(), envelope_keys, &mut |v| {
+            values.push(v)
+        })?;
+        Ok(values)
+    }
+
+    #[test]
+    fn streams_envelope_and_array_elements() {
+        let batch = "{\n  \"Records\": [\n    {\"eventName\": \"A\"},\n    {\"eventName\": \"B\"}\n  ]\n}\n";
+        let values = collect(batch, &["Records"]).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                serde_json::json!({"eventName": "A"}),
+                serde_json::json!({"eventName": "B"})
+            ]
+        );
+
+        let array =
+            "[\n {\"eventName\": \"A\"},\n {\"eventName\": \"B\"},\n {\"eventName\": \"C\"}\n]";
+        assert_eq!(collect(array, &[]).unwrap().len(), 3);
+
+        // Without a matching envelope key the document is emitted whole.
+        let values = collect(batch, &["records"]).unwrap();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0]["Records"][1]["eventName"], "B");
+    }
+
+    #[test]
+    fn envelope_keeps_a_non_array_value() {
+        let doc = "{\n \"items\": {\"not\": \"an array\"},\n \"kind\": \"x\"\n}";
+        let values = collect(doc, &["items"]).unwrap();
+        assert_eq!(
+            values,
+            vec![serde_json::json!({"items": {"not": "an array"}, "kind": "x"})]
+        );
+    }
+
+    #[test]
+    fn reads_jsonl_line_by_line() {
+        let jsonl = "\u{FEFF}{\"a\":1}\n\nnot json\n2024-01-15T03:04:05.123Z {\"a\":2}\n{\"a\":3}{\"a\":4}\n";
+        let values = collect(jsonl, &["Records"]).unwrap();
+        let a: Vec<i64> = values.iter().map(|v| v["a"].as_i64().unwrap()).collect();
+        assert_eq!(a, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn overlong_line_is_an_error() {
+        let mut line = String::new();
+        let mut input = &b"{\"a\":1}\n{\"eventName\":\"StopLogging\"}\n"[..];
+        assert!(read_line_capped(&mut input, &mut line, 16).unwrap());
+        let err = read_line_capped(&mut input, &mut line, 16).unwrap_err();
+        assert!(
+            err.to_string().contains("possible decompression bomb"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn reads_concatenated_pretty_printed_documents() {
+        let docs = "{\n \"Records\": [{\"a\": 1}]\n}\n{\n \"Records\": [{\"a\": 2}]\n}\n";
+        assert_eq!(collect(docs, &["Records"]).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn large_input_is_streamed() {
+        // Above the in-memory limit the same input is parsed incrementally.
+        let batch = "{\"Records\": [{\"eventName\": \"A\"}, {\"eventName\": \"B\"}]}\n{\"Records\": [{\"eventName\": \"C\"}]}";
+        let mut values = Vec::new();
+        stream_json_with_limit(
+            &mut batch.as_bytes(),
+            &["Records"],
+            &mut |v| values.push(v),
+            8,
+        )
+        .unwrap();
+        let names: Vec<&str> = values
+            .iter()
+            .map(|v| v["eventName"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["A", "B", "C"]);
+
+        let jsonl = "2024-01-15T03:04:05.123Z {\"a\":1}\n2024-01-15T03:04:06.000Z {\"a\":2}\n";
+        let mut values = Vec::new();
+        stream_json_with_limit(&mut jsonl.as_bytes(), &[], &mut |v| values.push(v), 8).unwrap();
+        assert_eq!(values.len(), 2);
+    }
+
+    #[test]
+    fn large_jsonl_skips_a_malformed_line() {
+        let jsonl = "{\"Records\": [{\"eventName\": \"A\"}]}\n{\"Records\": [\n{\"Records\": [{\"eventName\": \"C\"}]}\n";
+        let mut values = Vec::new();
+        stream_json_with_limit(
+            &mut jsonl.as_bytes(),
+            &["Records"],
+            &mut |v| values.push(v),
+            40,
+        )
+        .unwrap();
+        let names: Vec<&str> = values
+            .iter()
+            .map(|v| v["eventName"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["A", "C"]);
+    }
+
+    #[test]
+    fn malformed_large_document_is_an_error_after_emitting_what_parsed() {
+        let mut values = Vec::new();
+        let truncated = "{\n \"Records\": [{\"a\": 1}, {\"a\": ";
+        let result = stream_json_with_limit(
+            &mut truncated.as_bytes(),
+            &["Records"],
+            &mut |v| values.push(v),
+            8,
+        );
+        assert!(result.is_err());
+        assert_eq!(values.len(), 1);
+    }
+}
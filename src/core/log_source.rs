@@ -1,17 +1,41 @@
-use sigma_rust::Event;
+use sigma_rust::{Event, Rule};
 
 pub enum LogSource {
     Aws,
     Azure,
+    Gcp,
+    Okta,
+    Gws,
+    Kubernetes,
+    VpcFlow,
+    S3Access,
     All,
 }
 
 impl LogSource {
+    /// The individual log sources that `All` (`cloud-timeline`) combines.
+    pub const SOURCES: [LogSource; 8] = [
+        LogSource::Aws,
+        LogSource::Azure,
+        LogSource::Gcp,
+        LogSource::Okta,
+        LogSource::Gws,
+        LogSource::Kubernetes,
+        LogSource::VpcFlow,
+        LogSource::S3Access,
+    ];
+
     pub fn profile_path(&self) -> &str {
         match self {
             LogSource::Aws => "config/aws_profile.yaml",
             LogSource::Azure => "config/azure_profile.yaml",
-            LogSource::All => "",
+            LogSource::Gcp => "config/gcp_profile.yaml",
+            LogSource::Okta => "config/okta_profile.yaml",
+            LogSource::Gws => "config/gws_profile.yaml",
+            LogSource::Kubernetes => "config/k8s_profile.yaml",
+            LogSource::VpcFlow => "config/vpcflow_profile.yaml",
+            LogSource::S3Access => "config/s3access_profile.yaml",
+            LogSource::All => "config/cloud_profile.yaml",
         }
     }
 
@@ -20,6 +44,12 @@ impl LogSource {
         match self {
             LogSource::Aws => "aws_ignore_rule_list.txt",
             LogSource::Azure => "azure_ignore_rule_list.txt",
+            LogSource::Gcp => "gcp_ignore_rule_list.txt",
+            LogSource::Okta => "okta_ignore_rule_list.txt",
+            LogSource::Gws => "gws_ignore_rule_list.txt",
+            LogSource::Kubernetes => "k8s_ignore_rule_list.txt",
+            LogSource::VpcFlow => "vpcflow_ignore_rule_list.txt",
+            LogSource::S3Access => "s3access_ignore_rule_list.txt",
             LogSource::All => "",
         }
     }
@@ -39,6 +69,26 @@ impl LogSource {
                 "riskdetection",
                 "pim",
             ],
+            LogSource::Gcp => &[
+                "gcp.audit",
+                "admin_activity",
+                "data_access",
+                "system_event",
+                "policy",
+            ],
+            LogSource::Okta => &["okta"],
+            LogSource::Gws => &[
+                "google_workspace",
+                "google_workspace.login",
+                "google_workspace.admin",
+                "google_workspace.drive",
+                "google_workspace.token",
+            ],
+            // Kubernetes rules are selected by `product: kubernetes` instead (see
+            // `is_supported_rule`), since their `service: audit` clashes with the m365 one.
+            LogSource::Kubernetes => &[],
+            LogSource::VpcFlow => &["vpcflowlogs"],
+            LogSource::S3Access => &["s3accesslogs"],
             LogSource::All => &[
                 "cloudtrail",
                 "activitylogs",
@@ -51,15 +101,63 @@ impl LogSource {
                 "threat_management",
                 "riskdetection",
                 "pim",
+                "gcp.audit",
+                "admin_activity",
+                "data_access",
+                "system_event",
+                "policy",
+                "okta",
+                "google_workspace",
+                "google_workspace.login",
+                "google_workspace.admin",
+                "google_workspace.drive",
+                "google_workspace.token",
+                "vpcflowlogs",
+                "s3accesslogs",
             ],
         }
     }
+
+    /// True if `rule` should be loaded for this log source. Kubernetes audit rules are
+    /// identified by `product: kubernetes`; every other rule by its `service`.
+    pub fn is_supported_rule(&self, rule: &Rule) -> bool {
+        if rule.logsource.product.as_deref() == Some("kubernetes") {
+            return matches!(self, LogSource::Kubernetes | LogSource::All);
+        }
+        rule.logsource
+            .service
+            .as_deref()
+            .is_some_and(|service| self.supported_services().contains(&service))
+    }
+}
+
+/// True if `event` is the kind of log `rule`'s logsource targets. `product: kubernetes` rules
+/// only match Kubernetes audit events; all others are routed by `is_match_service`.
+pub fn is_match_logsource(rule: &Rule, event: &Event) -> bool {
+    if rule.logsource.product.as_deref() == Some("kubernetes") {
+        return is_k8s_audit_event(event);
+    }
+    is_match_service(&rule.logsource.service, event)
+}
+
+/// Kubernetes API server audit events (`audit.k8s.io/v1` `Event`) all carry an `auditID`.
+fn is_k8s_audit_event(event: &Event) -> bool {
+    event.get("auditID").is_some()
+        || event
+            .get("apiVersion")
+            .is_some_and(|v| v.value_to_string().starts_with("audit.k8s.io/"))
 }
 
 pub fn is_match_service(service: &Option<String>, event: &Event) -> bool {
     if let Some(s) = service {
         match s.as_str() {
-            "cloudtrail" => true,
+            // CloudTrail records (after `CloudTrailEvent` and OCSF records are normalized), judged
+            // the same way `cloud-timeline` detects them, so `cloudtrail` rules never match the
+            // other sources' records.
+            "cloudtrail" => {
+                event.get("eventSource").is_some()
+                    && (event.get("eventVersion").is_some() || event.get("eventTime").is_some())
+            }
             "activitylogs" => {
                 event
                     .get("category")
@@ -95,6 +193,31 @@ pub fn is_match_service(service: &Option<String>, event: &Event) -> bool {
             // incidents share the Microsoft Graph risk-event schema, identified by
             // `riskEventType`. The rule's specific `riskEventType` value selects the sub-type.
             "riskdetection" | "pim" => event.get("riskEventType").is_some(),
+            // GCP Cloud Audit Logs. SigmaHQ's upstream gcp rules use `gcp.audit` for every
+            // audit stream; the per-stream services select one stream by its `logName`.
+            "gcp.audit" => gcp_audit_log_kind(event).is_some(),
+            "admin_activity" => gcp_audit_log_kind(event).as_deref() == Some("activity"),
+            "data_access" | "system_event" | "policy" => {
+                gcp_audit_log_kind(event).as_deref() == Some(s.as_str())
+            }
+            // Okta System Log events (`/api/v1/logs`), identified by `eventType` plus the
+            // `published` timestamp that every System Log event carries.
+            "okta" => event.get("eventType").is_some() && event.get("published").is_some(),
+            // Google Workspace Admin SDK Reports activities. The per-application services select
+            // one report (`login`, `admin`, `drive`, `token`) by the activity's `applicationName`.
+            "google_workspace" => event.get("id.applicationName").is_some(),
+            "google_workspace.login"
+            | "google_workspace.admin"
+            | "google_workspace.drive"
+            | "google_workspace.token" => event.get("id.applicationName").is_some_and(|v| {
+                Some(v.value_to_string().as_str()) == s.strip_prefix("google_workspace.")
+            }),
+            // VPC Flow Logs records (parsed from the space-delimited text format).
+            "vpcflowlogs" => event.get("interface-id").is_some() || event.get("srcaddr").is_some(),
+            // S3 server access log records (parsed from the access log line format).
+            "s3accesslogs" => {
+                event.get("bucketOwner").is_some() && event.get("operation").is_some()
+            }
             _ => false,
         }
     } else {
@@ -102,6 +225,19 @@ pub fn is_match_service(service: &Option<String>, event: &Event) -> bool {
     }
 }
 
+/// The Cloud Audit Logs stream (`activity`, `data_access`, `system_event` or `policy`) named by
+/// a Cloud Logging entry's `logName`, e.g. `projects/p/logs/cloudaudit.googleapis.com%2Factivity`.
+/// Exports keep the `/` URL-encoded, but some tools decode it, so both spellings are accepted.
+fn gcp_audit_log_kind(event: &Event) -> Option<String> {
+    let log_name = event.get("logName")?.value_to_string();
+    let (_, kind) = log_name.split_once("cloudaudit.googleapis.com")?;
+    let kind = kind
+        .strip_prefix("%2F")
+        .or_else(|| kind.strip_prefix("%2f"))
+        .or_else(|| kind.strip_prefix('/'))?;
+    Some(kind.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,4 +296,129 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn gcp_services_route_by_audit_log_name() {
+        let activity = ev(
+            r#"{"logName":"projects/p/logs/cloudaudit.googleapis.com%2Factivity","protoPayload":{"methodName":"SetIamPolicy"}}"#,
+        );
+        assert!(is_match_service(&Some("gcp.audit".to_string()), &activity));
+        assert!(is_match_service(
+            &Some("admin_activity".to_string()),
+            &activity
+        ));
+        assert!(!is_match_service(
+            &Some("data_access".to_string()),
+            &activity
+        ));
+
+        // Some tools decode the `%2F` in `logName`.
+        let data_access =
+            ev(r#"{"logName":"projects/p/logs/cloudaudit.googleapis.com/data_access"}"#);
+        assert!(is_match_service(
+            &Some("data_access".to_string()),
+            &data_access
+        ));
+        assert!(!is_match_service(&Some("policy".to_string()), &data_access));
+    }
+
+    #[test]
+    fn gcp_services_do_not_match_non_audit_entries() {
+        let e = ev(r#"{"logName":"projects/p/logs/stdout","textPayload":"hello"}"#);
+        for svc in [
+            "gcp.audit",
+            "admin_activity",
+            "data_access",
+            "system_event",
+            "policy",
+        ] {
+            assert!(
+                !is_match_service(&Some(svc.to_string()), &e),
+                "service {svc} should not match a non-audit log entry"
+            );
+        }
+    }
+
+    #[test]
+    fn okta_service_matches_system_log_events() {
+        let e = ev(
+            r#"{"published":"2024-01-15T03:04:05.123Z","eventType":"user.session.start","actor":{"alternateId":"alice@example.com"}}"#,
+        );
+        assert!(is_match_service(&Some("okta".to_string()), &e));
+        assert!(!is_match_service(&Some("signinlogs".to_string()), &e));
+
+        // A CloudTrail record also has an `eventType`, but no `published` timestamp.
+        let ct = ev(r#"{"eventType":"AwsApiCall","eventTime":"2024-01-15T03:04:05Z"}"#);
+        assert!(!is_match_service(&Some("okta".to_string()), &ct));
+    }
+
+    #[test]
+    fn gws_services_route_by_application_name() {
+        let login = ev(
+            r#"{"id":{"time":"2024-03-01T09:15:00.000Z","applicationName":"login"},"eventName":"login_success"}"#,
+        );
+        assert!(is_match_service(
+            &Some("google_workspace".to_string()),
+            &login
+        ));
+        assert!(is_match_service(
+            &Some("google_workspace.login".to_string()),
+            &login
+        ));
+        assert!(!is_match_service(
+            &Some("google_workspace.drive".to_string()),
+            &login
+        ));
+
+        // Okta and CloudTrail events carry no Reports API `id.applicationName`.
+        let okta =
+            ev(r#"{"published":"2024-01-15T03:04:05.123Z","eventType":"user.session.start"}"#);
+        assert!(!is_match_service(
+            &Some("google_workspace".to_string()),
+            &okta
+        ));
+    }
+
+    #[test]
+    fn cloudtrail_rules_only_match_cloudtrail_records() {
+        let rule = sigma_rust::rule_from_yaml(
+            "title: t\nlogsource:\n  product: aws\n  service: cloudtrail\ndetection:\n  selection:\n    operationName: Delete\n  condition: selection\n",
+        )
+        .unwrap();
+        let cloudtrail = ev(
+            r#"{"eventVersion":"1.08","eventSource":"iam.amazonaws.com","eventName":"DeleteUser"}"#,
+        );
+        let azure = ev(r#"{"category":"Administrative","operationName":"Delete"}"#);
+
+        assert!(LogSource::All.is_supported_rule(&rule));
+        assert!(is_match_logsource(&rule, &cloudtrail));
+        assert!(!is_match_logsource(&rule, &azure));
+    }
+
+    #[test]
+    fn kubernetes_rules_route_by_product() {
+        let k8s =
+            ev(r#"{"kind":"Event","apiVersion":"audit.k8s.io/v1","auditID":"a1","verb":"create"}"#);
+        let ual = ev(r#"{"Workload":"Exchange","RecordType":1}"#);
+        let k8s_rule = sigma_rust::rule_from_yaml(
+            "title: t\nlogsource:\n  product: kubernetes\n  service: audit\ndetection:\n  selection:\n    verb: create\n  condition: selection\n",
+        )
+        .unwrap();
+        let m365_rule = sigma_rust::rule_from_yaml(
+            "title: t\nlogsource:\n  product: m365\n  service: audit\ndetection:\n  selection:\n    Workload: Exchange\n  condition: selection\n",
+        )
+        .unwrap();
+
+        // Both rules share `service: audit`; only the product tells them apart.
+        assert!(LogSource::Kubernetes.is_supported_rule(&k8s_rule));
+        assert!(!LogSource::Kubernetes.is_supported_rule(&m365_rule));
+        assert!(!LogSource::Azure.is_supported_rule(&k8s_rule));
+        assert!(LogSource::Azure.is_supported_rule(&m365_rule));
+        assert!(LogSource::All.is_supported_rule(&k8s_rule));
+
+        assert!(is_match_logsource(&k8s_rule, &k8s));
+        assert!(!is_match_logsource(&k8s_rule, &ual));
+        assert!(is_match_logsource(&m365_rule, &ual));
+        assert!(!is_match_logsource(&m365_rule, &k8s));
+    }
 }
@@ -0,0 +1,239 @@
+use crate::core::util::FileDigest;
+use crate::option::cli::FULL_VERSION;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Options whose values are credentials, and are written as `REDACTED` in the manifest.
+const SECRET_OPTIONS: [&str; 4] = [
+    "--s3-secret-key",
+    "--s3-session-token",
+    "--azure-sas-token",
+    "--azure-account-key",
+];
+
+/// Whether the input and skipped files are recorded, see [`start_recording`].
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// The files skipped so far in this run, in the order they were reported.
+static SKIPPED_FILES: Mutex<Vec<SkippedFile>> = Mutex::new(Vec::new());
+
+/// The input files read so far in this run, hashed as they were read.
+static INPUT_FILES: Mutex<Vec<InputFileEntry>> = Mutex::new(Vec::new());
+
+/// What a run read and how, written next to the `--output` file so the results can be tied
+/// back to exactly the evidence and rules that produced them.
+#[derive(Debug, Serialize)]
+pub struct RunManifest {
+    pub suzaku_version: String,
+    /// The command line, with credential values redacted.
+    pub command_line: Vec<String>,
+    pub rules_path: PathBuf,
+    /// HEAD commit of the rules directory, if it is a git checkout (as `update-rules` makes it).
+    pub rules_git_commit: Option<String>,
+    pub started: String,
+    pub finished: String,
+    pub output_files: Vec<PathBuf>,
+    pub input_files: Vec<InputFileEntry>,
+    pub skipped_files: Vec<SkippedFile>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct InputFileEntry {
+    /// Local path or remote URL. Stdin is recorded as `-`.
+    pub path: String,
+    /// Size and SHA-256 (hex encoded) of the file as stored, before decompression. Both are
+    /// `None` if the file could not be read to the end.
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+}
+
+/// Start recording the input and skipped files, for a run that writes a manifest. Until then
+/// they are not kept, so runs without one (and `--follow`, which never ends) do not collect them.
+pub fn start_recording() {
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+/// Record the input `path` with its digest (`None` if it could not be read to the end), for the
+/// manifest.
+pub fn record_input(path: &str, digest: Option<FileDigest>) {
+    if !RECORDING.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Ok(mut inputs) = INPUT_FILES.lock() {
+        inputs.push(InputFileEntry {
+            path: path.to_string(),
+            size: digest.as_ref().map(|d| d.size),
+            sha256: digest.map(|d| d.sha256),
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Warn that `path` is skipped, and record it for the manifest.
+pub fn warn_skipped(path: &str, reason: &str) {
+    eprintln!("[WARNING] Skipping {path}: {reason}");
+    record_skipped(path, reason);
+}
+
+/// Record that `path` was skipped (or read only in part), for the manifest.
+pub fn record_skipped(path: &str, reason: &str) {
+    if !RECORDING.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Ok(mut skipped) = SKIPPED_FILES.lock() {
+        skipped.push(SkippedFile {
+            path: path.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+/// The manifest path for `output`: `out.csv` gets `out.manifest.json`.
+pub fn manifest_path(output: &Path) -> PathBuf {
+    output.with_extension("manifest.json")
+}
+
+/// Build the manifest of this run from the input files recorded as they were read, in path
+/// order.
+pub fn build_manifest(
+    rules: &Path,
+    output_files: &[PathBuf],
+    started: DateTime<Utc>,
+) -> RunManifest {
+    let mut input_files = INPUT_FILES.lock().map(|s| s.clone()).unwrap_or_default();
+    input_files.sort_by(|a, b| a.path.cmp(&b.path));
+    let skipped_files = SKIPPED_FILES.lock().map(|s| s.clone()).unwrap_or_default();
+    RunManifest {
+        suzaku_version: FULL_VERSION.to_string(),
+        command_line: redact_command_line(env::args()),
+        rules_path: rules.to_path_buf(),
+        rules_git_commit: rules_git_commit(rules),
+        started: started.to_rfc3339_opts(SecondsFormat::Secs, true),
+        finished: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        output_files: output_files.to_vec(),
+        input_files,
+        skipped_files,
+    }
+}
+
+/// Write `manifest` next to `output`, and return where it was written.
+pub fn write_manifest(manifest: &RunManifest, output: &Path) -> Result<PathBuf, String> {
+    let path = manifest_path(output);
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Cannot write run manifest {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+fn rules_git_commit(rules: &Path) -> Option<String> {
+    let repo = git2::Repository::discover(rules).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+/// The command line with the values of [`SECRET_OPTIONS`] and the query strings of URLs (which
+/// carry SAS tokens) replaced by `REDACTED`.
+fn redact_command_line(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut redact_next = false;
+    args.map(|arg| {
+        if redact_next {
+            redact_next = false;
+            return "REDACTED".to_string();
+        }
+        if let Some((name, _)) = arg.split_once('=')
+            && SECRET_OPTIONS.contains(&name)
+        {
+            return format!("{name}=REDACTED");
+        }
+        if SECRET_OPTIONS.contains(&arg.as_str()) {
+            redact_next = true;
+            return arg;
+        }
+        match arg.split_once('?') {
+            Some((url, _)) if url.contains("://") => format!("{url}?REDACTED"),
+            _ => arg,
+        }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_credentials_and_url_queries() {
+        let args = [
+            "suzaku",
+            "aws-ct-timeline",
+            "--s3-secret-key",
+            "abc",
+            "--azure-account-key=xyz",
+            "--azure-blob",
+            "https://a.blob.core.windows.net/logs/?sv=2022&sig=s",
+            "-o",
+            "out.csv",
+        ]
+        .map(String::from);
+        assert_eq!(
+            redact_command_line(args.into_iter()),
+            vec![
+                "suzaku",
+                "aws-ct-timeline",
+                "--s3-secret-key",
+                "REDACTED",
+                "--azure-account-key=REDACTED",
+                "--azure-blob",
+                "https://a.blob.core.windows.net/logs/?REDACTED",
+                "-o",
+                "out.csv",
+            ]
+        );
+    }
+
+    /// The recorded entry for `path`.
+    pub(crate) fn recorded_input(path: &str) -> Option<InputFileEntry> {
+        let inputs = INPUT_FILES.lock().unwrap();
+        inputs.iter().find(|entry| entry.path == path).cloned()
+    }
+
+    #[test]
+    fn hashing_reader_hashes_the_whole_input() {
+        use crate::core::util::HashingReader;
+        use std::io::Read;
+        let data = b"{\"a\":1}\nnot json\n{\"b\":2}\n";
+        let mut reader = HashingReader::new(&data[..]);
+        // The parser stops early; the rest is still hashed.
+        reader.read_exact(&mut [0; 4]).unwrap();
+        start_recording();
+        record_input("hashing-reader-test", reader.finish());
+        let entry = recorded_input("hashing-reader-test").unwrap();
+        assert_eq!(entry.size, Some(data.len() as u64));
+        assert_eq!(
+            entry.sha256.as_deref(),
+            Some("2c09040d0947a1c5dac99ce8468f7bccbd078b41a3f5fe88ae65ebad9155d714")
+        );
+    }
+
+    #[test]
+    fn manifest_is_written_next_to_the_output() {
+        assert_eq!(
+            manifest_path(Path::new("out/timeline.csv")),
+            PathBuf::from("out/timeline.manifest.json")
+        );
+        assert_eq!(
+            manifest_path(Path::new("timeline")),
+            PathBuf::from("timeline.manifest.json")
+        );
+    }
+}
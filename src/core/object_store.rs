@@ -0,0 +1,274 @@
+use regex::Regex;
+use std::io::{self, Read};
+use std::sync::LazyLock;
+use std::thread;
+use std::time::Duration;
+
+/// Attempts per request before a dropped connection or server error is given up on.
+const MAX_ATTEMPTS: u32 = 4;
+
+/// The `<Error>` body S3 and Azure Storage both return on failure.
+static ERROR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)<Code>(.*?)</Code>.*?<Message>(.*?)</Message>")
+        .expect("ERROR_RE regex pattern is invalid")
+});
+
+/// One object in remote storage.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteObject {
+    pub key: String,
+    pub size: u64,
+}
+
+/// One page-merged listing: the objects under a prefix and, for a `/`-delimited listing, the
+/// sub-prefixes ("directories") directly below it.
+#[derive(Debug, Default)]
+pub struct Listing {
+    pub objects: Vec<RemoteObject>,
+    pub prefixes: Vec<String>,
+}
+
+/// Remote storage that log objects are listed and streamed from, instead of a local directory.
+pub trait ObjectStore: Sync {
+    /// URL of `key` as shown to the user (e.g. `s3://bucket/AWSLogs/...`). Extension and path
+    /// date filters are matched against it.
+    fn url(&self, key: &str) -> String;
+
+    /// List everything under `prefix`, following pagination. With `delimited`, only the objects
+    /// directly under `prefix` are returned, along with the sub-prefixes below it.
+    fn list(&self, prefix: &str, delimited: bool) -> io::Result<Listing>;
+
+    /// Open the object `key` for reading as it downloads.
+    fn get(&self, key: &str) -> io::Result<Box<dyn Read + Send>>;
+}
+
+/// List the objects under `prefix` whose URL `keep_object` accepts, in key order.
+///
+/// With `prune`, the listing descends one `/` level at a time and skips every sub-prefix
+/// `keep_prefix` rejects (e.g. the days outside `--file-date-from`/`--file-date-to`), so those
+/// objects are never listed. Otherwise everything under `prefix` is listed in one pass, which
+/// takes fewer requests than walking each level.
+pub fn list_objects(
+    store: &dyn ObjectStore,
+    prefix: &str,
+    prune: bool,
+    keep_prefix: &dyn Fn(&str) -> bool,
+    keep_object: &dyn Fn(&str) -> bool,
+) -> io::Result<Vec<RemoteObject>> {
+    let mut objects = Vec::new();
+    let mut pending = vec![prefix.to_string()];
+    while let Some(prefix) = pending.pop() {
+        let listing = store.list(&prefix, prune)?;
+        objects.extend(
+            listing
+                .objects
+                .into_iter()
+                .filter(|o| keep_object(&store.url(&o.key))),
+        );
+        pending.extend(
+            listing
+                .prefixes
+                .into_iter()
+                .filter(|p| keep_prefix(&store.url(p))),
+        );
+    }
+    objects.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(objects)
+}
+
+/// An HTTP agent for object storage requests. Error statuses are returned as responses, so their
+/// `<Error>` body can be reported.
+pub fn http_agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .timeout_connect(Some(Duration::from_secs(30)))
+        .build()
+        .into()
+}
+
+/// Call `send` until it succeeds, retrying connections dropped by the server and 5xx responses
+/// (e.g. `503 SlowDown`, `503 ServerBusy`) with a growing delay.
+pub fn send_with_retries(
+    send: impl Fn() -> Result<ureq::http::Response<ureq::Body>, ureq::Error>,
+) -> Result<ureq::http::Response<ureq::Body>, ureq::Error> {
+    let mut attempt = 1;
+    loop {
+        let result = send();
+        let retry = match &result {
+            Ok(response) => response.status().is_server_error(),
+            Err(_) => true,
+        };
+        if !retry || attempt == MAX_ATTEMPTS {
+            return result;
+        }
+        thread::sleep(Duration::from_millis(200 << attempt));
+        attempt += 1;
+    }
+}
+
+/// Describe a failed response: its status, and the message and code from its `<Error>` body.
+pub fn error_message(response: &mut ureq::http::Response<ureq::Body>) -> String {
+    let status = response.status();
+    let body = response.body_mut().read_to_string().unwrap_or_default();
+    match ERROR_RE.captures(&body) {
+        Some(caps) => format!("{status}: {} ({})", xml_unescape(&caps[2]), &caps[1]),
+        None => status.to_string(),
+    }
+}
+
+/// Percent-encode `s` the way S3 (SigV4) and Azure Storage canonicalize URLs: everything but
+/// the unreserved characters, and `/` too unless it separates key path segments.
+pub fn uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+/// Decode the XML entities in listing text.
+pub fn xml_unescape(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// An in-memory store that records the prefixes it was asked to list.
+    pub struct MemoryStore {
+        pub objects: Vec<(String, Vec<u8>)>,
+        pub listed: Mutex<Vec<String>>,
+    }
+
+    impl MemoryStore {
+        pub fn new(objects: &[(&str, &[u8])]) -> Self {
+            MemoryStore {
+                objects: objects
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_vec()))
+                    .collect(),
+                listed: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl ObjectStore for MemoryStore {
+        fn url(&self, key: &str) -> String {
+            format!("mem://bucket/{key}")
+        }
+
+        fn list(&self, prefix: &str, delimited: bool) -> io::Result<Listing> {
+            self.listed.lock().unwrap().push(prefix.to_string());
+            let mut listing = Listing::default();
+            for (key, data) in &self.objects {
+                let Some(rest) = key.strip_prefix(prefix) else {
+                    continue;
+                };
+                match rest.find('/') {
+                    Some(i) if delimited => {
+                        let sub = format!("{prefix}{}", &rest[..=i]);
+                        if !listing.prefixes.contains(&sub) {
+                            listing.prefixes.push(sub);
+                        }
+                    }
+                    _ => listing.objects.push(RemoteObject {
+                        key: key.clone(),
+                        size: data.len() as u64,
+                    }),
+                }
+            }
+            Ok(listing)
+        }
+
+        fn get(&self, key: &str) -> io::Result<Box<dyn Read + Send>> {
+            let (_, data) = self
+                .objects
+                .iter()
+                .find(|(k, _)| k == key)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, key.to_string()))?;
+            Ok(Box::new(io::Cursor::new(data.clone())))
+        }
+    }
+
+    #[test]
+    fn pruned_listing_skips_rejected_prefixes() {
+        let store = MemoryStore::new(&[
+            ("logs/2024/01/01/a.json", b"{}"),
+            ("logs/2024/01/02/b.json", b"{}"),
+            ("logs/2024/02/01/c.json", b"{}"),
+            ("logs/readme.md", b""),
+        ]);
+        let objects = list_objects(&store, "logs/", true, &|p| !p.ends_with("/02/"), &|url| {
+            url.ends_with(".json")
+        })
+        .unwrap();
+        let keys: Vec<&str> = objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["logs/2024/01/01/a.json"]);
+        let listed = store.listed.lock().unwrap();
+        assert!(!listed.iter().any(|p| p.starts_with("logs/2024/02/")));
+    }
+
+    #[test]
+    fn encodes_and_unescapes() {
+        assert_eq!(uri_encode("a b/c=d~", false), "a%20b/c%3Dd~");
+        assert_eq!(uri_encode("a/b", true), "a%2Fb");
+        assert_eq!(
+            xml_unescape("a&amp;b&#x26;c&#38;d&lt;&bogus;"),
+            "a&b&c&d<&bogus;"
+        );
+    }
+
+    #[test]
+    fn unpruned_listing_lists_everything_at_once() {
+        let store = MemoryStore::new(&[("b/2.json", b"{}"), ("a/1.json", b"{}")]);
+        let objects = list_objects(&store, "", false, &|_| false, &|_| true).unwrap();
+        let keys: Vec<&str> = objects.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(keys, vec!["a/1.json", "b/2.json"]);
+        assert_eq!(*store.listed.lock().unwrap(), vec![String::new()]);
+    }
+}
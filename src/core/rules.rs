@@ -1,7 +1,12 @@
 use crate::core::log_source::LogSource;
+use crate::core::timeline_writer::abbreviate_tag;
+use crate::option::timefiler::parse_offset;
+use chrono::Duration;
+use serde::Deserialize;
 use sigma_rust::Rule;
 use sigma_rust::rule_from_yaml;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -38,6 +43,25 @@ fn load_correlation_yamls_recursive(directory: &PathBuf, yaml_contents: &mut Vec
     }
 }
 
+/// The longest `timespan` of the correlations in `yamls`, or `None` if none has one that parses.
+/// Matched events older than this before the newest one cannot complete a correlation any more.
+pub fn max_correlation_timespan(yamls: &[String]) -> Option<Duration> {
+    yamls
+        .iter()
+        .flat_map(|yaml| yaml_serde::Deserializer::from_str(yaml))
+        .filter_map(|doc| yaml_serde::Value::deserialize(doc).ok())
+        .filter_map(|doc| parse_timespan(doc.get("correlation")?.get("timespan")?.as_str()?))
+        .max()
+}
+
+/// A Sigma correlation `timespan`: `30s`, `5m`, `1h` or `1d`.
+fn parse_timespan(timespan: &str) -> Option<Duration> {
+    match timespan.trim().strip_suffix('s') {
+        Some(seconds) => Duration::try_seconds(seconds.parse().ok()?),
+        None => parse_offset(timespan),
+    }
+}
+
 fn contains_correlation_key(yaml_content: &str) -> bool {
     yaml_content.lines().any(|line| {
         let trimmed = line.trim();
@@ -50,8 +74,7 @@ pub fn load_rules_from_dir(path: &PathBuf, log: &LogSource) -> Vec<Rule> {
     if path.is_file() {
         if let Ok(contents) = fs::read_to_string(path)
             && let Ok(rule) = rule_from_yaml(&contents)
-            && let Some(service) = &rule.logsource.service
-            && log.supported_services().contains(&service.as_str())
+            && log.is_supported_rule(&rule)
         {
             rules.push(rule);
         }
@@ -68,8 +91,7 @@ fn load_rules_recursive(directory: &PathBuf, rules: &mut Vec<Rule>, log: &LogSou
             if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("yml") {
                 if let Ok(contents) = fs::read_to_string(&path)
                     && let Ok(rule) = rule_from_yaml(&contents)
-                    && let Some(service) = &rule.logsource.service
-                    && log.supported_services().contains(&service.as_str())
+                    && log.is_supported_rule(&rule)
                 {
                     rules.push(rule);
                 }
@@ -94,29 +116,131 @@ pub fn ignore_rule_list_path(rules_path: &Path, log: &LogSource) -> PathBuf {
     base.join("config").join(log.ignore_rule_list_filename())
 }
 
+/// The rule UUIDs to skip for `log`, read from its ignore-list file under `rules_path`. For
+/// `LogSource::All`, the ignore lists of every log source are combined.
+pub fn load_log_ignore_rule_ids(rules_path: &Path, log: &LogSource) -> HashSet<String> {
+    match log {
+        LogSource::All => LogSource::SOURCES
+            .iter()
+            .flat_map(|log| load_ignore_rule_ids(&ignore_rule_list_path(rules_path, log)))
+            .collect(),
+        _ => load_ignore_rule_ids(&ignore_rule_list_path(rules_path, log)),
+    }
+}
+
 /// Read a set of rule UUIDs to skip from an ignore-list file. Format: one UUID per line;
 /// blank lines and lines starting with `#` are ignored; an inline `# comment` after the
 /// UUID is allowed. Returns an empty set if the file does not exist.
 pub fn load_ignore_rule_ids(path: &Path) -> HashSet<String> {
+    fs::read_to_string(path)
+        .map(|contents| parse_rule_id_list(&contents))
+        .unwrap_or_default()
+}
+
+/// Read a rule UUID list given on the command line (`--include-rules`/`--exclude-rules`), in
+/// the ignore-list format. Unlike the ignore lists, the file must exist.
+pub fn read_rule_id_list(path: &Path) -> Result<HashSet<String>, String> {
+    fs::read_to_string(path)
+        .map(|contents| parse_rule_id_list(&contents))
+        .map_err(|e| format!("Cannot read rule list {}: {e}", path.display()))
+}
+
+fn parse_rule_id_list(contents: &str) -> HashSet<String> {
     let mut ids = HashSet::new();
-    if let Ok(contents) = fs::read_to_string(path) {
-        for line in contents.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
-            if let Some(id) = line
-                .split('#')
-                .next()
-                .and_then(|s| s.split_whitespace().next())
-            {
-                ids.insert(id.to_string());
-            }
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(id) = line
+            .split('#')
+            .next()
+            .and_then(|s| s.split_whitespace().next())
+        {
+            ids.insert(id.to_string());
         }
     }
     ids
 }
 
+/// True if `--include-rules` selects the rule with `id`: it is listed, or no list was given.
+pub fn is_included(id: Option<&str>, include_ids: Option<&HashSet<String>>) -> bool {
+    include_ids.is_none_or(|ids| id.is_some_and(|id| ids.contains(id)))
+}
+
+/// Keep only the rules `--include-rules` lists.
+pub fn filter_included_rules(rules: Vec<Rule>, include_ids: Option<&HashSet<String>>) -> Vec<Rule> {
+    rules
+        .into_iter()
+        .filter(|rule| is_included(rule.id.as_deref(), include_ids))
+        .collect()
+}
+
+/// Read a `--level-override` file: one `<rule UUID>,<level>` per line, as in Hayabusa's
+/// `level_tuning.txt`. An `id,new_level` header, blank lines and `#` comments are skipped.
+/// Returns the new level of each rule, by UUID.
+pub fn read_level_overrides(path: &Path) -> Result<HashMap<String, String>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read level override file {}: {e}", path.display()))?;
+    let mut overrides = HashMap::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() || line.eq_ignore_ascii_case("id,new_level") {
+            continue;
+        }
+        let invalid = || {
+            format!(
+                "Invalid line {} in level override file {}: expected \"<rule UUID>,<level>\"",
+                i + 1,
+                path.display()
+            )
+        };
+        let (id, level) = line.split_once(',').ok_or_else(invalid)?;
+        let level = level_name(level.trim()).ok_or_else(invalid)?;
+        overrides.insert(id.trim().to_string(), level.to_string());
+    }
+    Ok(overrides)
+}
+
+/// Set the level of each rule listed in `overrides`, and return how many rules were changed.
+pub fn apply_level_overrides(rules: &mut [Rule], overrides: &HashMap<String, String>) -> usize {
+    // sigma-rust only parses levels as part of a rule, so each new level is taken from a minimal
+    // rule that has it.
+    let mut levels: HashMap<&str, Option<Rule>> = HashMap::new();
+    let mut changed = 0;
+    for rule in rules.iter_mut() {
+        let Some(level) = rule.id.as_deref().and_then(|id| overrides.get(id)) else {
+            continue;
+        };
+        let template = levels
+            .entry(level)
+            .or_insert_with(|| rule_from_yaml(&level_template_yaml(level)).ok());
+        if let Some(template) = template {
+            rule.level.clone_from(&template.level);
+            changed += 1;
+        }
+    }
+    changed
+}
+
+fn level_template_yaml(level: &str) -> String {
+    format!(
+        "title: {level}\nlevel: {level}\nlogsource:\n  product: any\ndetection:\n  selection:\n    field: value\n  condition: selection\n"
+    )
+}
+
+/// The Sigma spelling of `level`, which may be abbreviated as for `--min-level`.
+fn level_name(level: &str) -> Option<&'static str> {
+    match level_to_int(level) {
+        1 => Some("informational"),
+        2 => Some("low"),
+        3 => Some("medium"),
+        4 => Some("high"),
+        5 => Some("critical"),
+        _ => None,
+    }
+}
+
 /// True if `id` is present in the ignore set. Rules without an id are never ignored.
 pub fn is_ignored(id: Option<&str>, ignore_ids: &HashSet<String>) -> bool {
     id.map(|id| ignore_ids.contains(id)).unwrap_or(false)
@@ -133,6 +257,87 @@ pub fn filter_ignored_rules(rules: Vec<Rule>, ignore_ids: &HashSet<String>) -> V
         .collect()
 }
 
+/// True if `tag` is selected by `filter`: the full tag (compared case-insensitively, with `-` and
+/// `_` folded) or its abbreviation (`CredAccess`, `T1098`). A technique also selects its
+/// sub-techniques, so `attack.t1098` selects `attack.t1098.001`.
+fn tag_matches(tag: &str, filter: &str) -> bool {
+    let normalize = |s: &str| s.to_lowercase().replace('_', "-");
+    let filter = normalize(filter);
+    [normalize(tag), normalize(&abbreviate_tag(tag))]
+        .iter()
+        .any(|t| *t == filter || t.starts_with(&format!("{filter}.")))
+}
+
+/// True if a rule with `tags` passes `--include-tag`/`--exclude-tag`: with include filters it
+/// needs a tag one of them selects, and it must have no tag an exclude filter selects.
+pub fn is_selected_by_tags(
+    tags: Option<&[String]>,
+    include: &[String],
+    exclude: &[String],
+) -> bool {
+    let tags = tags.unwrap_or_default();
+    let has_tag = |filters: &[String]| {
+        tags.iter()
+            .any(|t| filters.iter().any(|f| tag_matches(t, f)))
+    };
+    (include.is_empty() || has_tag(include)) && !has_tag(exclude)
+}
+
+/// Drop the rules `--include-tag`/`--exclude-tag` do not select.
+pub fn filter_rules_by_tags(rules: Vec<Rule>, include: &[String], exclude: &[String]) -> Vec<Rule> {
+    if include.is_empty() && exclude.is_empty() {
+        return rules;
+    }
+    rules
+        .into_iter()
+        .filter(|rule| is_selected_by_tags(rule.tags.as_deref(), include, exclude))
+        .collect()
+}
+
+/// Statuses that are not loaded unless `--include-status` names them, as in Hayabusa.
+const STATUSES_EXCLUDED_BY_DEFAULT: [&str; 2] = ["deprecated", "unsupported"];
+
+/// True if a rule with `status` passes `--include-status`/`--exclude-status`. Without include
+/// filters, deprecated and unsupported rules are dropped, and rules without a status are kept.
+pub fn is_selected_by_status(status: Option<&str>, include: &[String], exclude: &[String]) -> bool {
+    let status = status.map(str::to_lowercase);
+    let listed = |statuses: &[String]| {
+        status
+            .as_ref()
+            .is_some_and(|s| statuses.iter().any(|l| l.eq_ignore_ascii_case(s)))
+    };
+    if listed(exclude) {
+        return false;
+    }
+    if !include.is_empty() {
+        return listed(include);
+    }
+    !status
+        .as_deref()
+        .is_some_and(|s| STATUSES_EXCLUDED_BY_DEFAULT.contains(&s))
+}
+
+/// The Sigma name (`test`, `deprecated`, ...) of a rule's or correlation rule's `status`, which
+/// sigma-rust gives as an enum for rules and as the YAML string for correlation rules.
+pub fn status_name(status: &impl Debug) -> String {
+    format!("{status:?}").trim_matches('"').to_lowercase()
+}
+
+/// Drop the rules `--include-status`/`--exclude-status` do not select.
+pub fn filter_rules_by_status(
+    rules: Vec<Rule>,
+    include: &[String],
+    exclude: &[String],
+) -> Vec<Rule> {
+    rules
+        .into_iter()
+        .filter(|rule| {
+            let status = rule.status.as_ref().map(status_name);
+            is_selected_by_status(status.as_deref(), include, exclude)
+        })
+        .collect()
+}
+
 fn level_to_int(level: &str) -> u8 {
     match level.to_lowercase().as_str() {
         "info" | "informational" => 1,
@@ -165,6 +370,23 @@ mod tests {
     use std::path::PathBuf;
     use tempfile::TempDir;
 
+    #[test]
+    fn max_correlation_timespan_is_the_longest_timespan() {
+        let correlation = |timespan: &str| {
+            format!(
+                "title: Base\nname: base\n---\ntitle: Many\ncorrelation:\n  type: event_count\n  rules:\n    - base\n  timespan: {timespan}\n"
+            )
+        };
+        let yamls = [correlation("90s"), correlation("1h"), correlation("5m")];
+        assert_eq!(max_correlation_timespan(&yamls), Some(Duration::hours(1)));
+        assert_eq!(
+            max_correlation_timespan(&yamls[..1]),
+            Some(Duration::seconds(90))
+        );
+        assert_eq!(max_correlation_timespan(&[correlation("soon")]), None);
+        assert_eq!(max_correlation_timespan(&[]), None);
+    }
+
     fn make_rule_with_level(level: Option<&str>) -> Rule {
         let yaml = format!(
             r#"
@@ -368,4 +590,108 @@ detection:
         let filtered = filter_rules_by_level(&rules, "critical");
         assert_eq!(filtered.len(), 1);
     }
+
+    #[test]
+    fn test_is_selected_by_tags() {
+        let tags = vec![
+            "attack.credential_access".to_string(),
+            "attack.t1098.001".to_string(),
+        ];
+        let s = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(is_selected_by_tags(Some(&tags), &[], &[]));
+        assert!(is_selected_by_tags(
+            Some(&tags),
+            &s(&["attack.credential-access"]),
+            &[]
+        ));
+        assert!(is_selected_by_tags(Some(&tags), &s(&["CredAccess"]), &[]));
+        assert!(is_selected_by_tags(Some(&tags), &s(&["attack.t1098"]), &[]));
+        assert!(is_selected_by_tags(
+            Some(&tags),
+            &s(&["Persis", "T1098.001"]),
+            &[]
+        ));
+        assert!(!is_selected_by_tags(
+            Some(&tags),
+            &s(&["attack.persistence"]),
+            &[]
+        ));
+        assert!(!is_selected_by_tags(Some(&tags), &s(&["attack.t109"]), &[]));
+        assert!(!is_selected_by_tags(None, &s(&["CredAccess"]), &[]));
+        assert!(!is_selected_by_tags(Some(&tags), &[], &s(&["credaccess"])));
+        assert!(is_selected_by_tags(None, &[], &s(&["CredAccess"])));
+    }
+
+    #[test]
+    fn test_is_selected_by_status() {
+        let s = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(is_selected_by_status(Some("stable"), &[], &[]));
+        assert!(is_selected_by_status(None, &[], &[]));
+        // Deprecated and unsupported rules are dropped unless asked for.
+        assert!(!is_selected_by_status(Some("Deprecated"), &[], &[]));
+        assert!(!is_selected_by_status(Some("unsupported"), &[], &[]));
+        assert!(is_selected_by_status(
+            Some("deprecated"),
+            &s(&["deprecated"]),
+            &[]
+        ));
+        assert!(!is_selected_by_status(Some("test"), &s(&["stable"]), &[]));
+        assert!(!is_selected_by_status(None, &s(&["stable"]), &[]));
+        assert!(!is_selected_by_status(
+            Some("Experimental"),
+            &[],
+            &s(&["experimental"])
+        ));
+        assert!(is_selected_by_status(
+            Some("test"),
+            &[],
+            &s(&["experimental"])
+        ));
+    }
+
+    #[test]
+    fn test_status_name() {
+        #[derive(Debug)]
+        enum Status {
+            Deprecated,
+        }
+        // Rules carry an enum, correlation rules the YAML string; both give the same name.
+        assert_eq!(status_name(&Status::Deprecated), "deprecated");
+        assert_eq!(status_name(&"deprecated".to_string()), "deprecated");
+    }
+
+    #[test]
+    fn test_include_rules_list() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("include.txt");
+        fs::write(&path, "# tuned for customer A\na # keep\n\nc\n").unwrap();
+        let ids = read_rule_id_list(&path).unwrap();
+        let rules = vec![
+            make_rule_with_id("a"),
+            make_rule_with_id("b"),
+            make_rule_with_id("c"),
+        ];
+        let kept = filter_included_rules(rules, Some(&ids));
+        let kept: Vec<_> = kept.iter().map(|r| r.id.as_deref().unwrap()).collect();
+        assert_eq!(kept, vec!["a", "c"]);
+        assert!(is_included(Some("b"), None));
+        assert!(read_rule_id_list(&temp_dir.path().join("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn test_level_overrides() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("levels.txt");
+        fs::write(&path, "id,new_level\na,crit # noisy in prod\nb, low\n").unwrap();
+        let overrides = read_level_overrides(&path).unwrap();
+        assert_eq!(overrides.get("a").map(String::as_str), Some("critical"));
+
+        let mut rules = vec![make_rule_with_id("a"), make_rule_with_id("b")];
+        assert_eq!(apply_level_overrides(&mut rules, &overrides), 2);
+        assert_eq!(filter_rules_by_level(&rules, "high").len(), 1);
+        assert_eq!(filter_rules_by_level(&rules, "low").len(), 2);
+
+        fs::write(&path, "a,urgent\n").unwrap();
+        assert!(read_level_overrides(&path).is_err());
+    }
 }
@@ -0,0 +1,204 @@
+use crate::core::json_stream;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use std::io::{self, BufRead};
+
+/// Field names of the S3 server access log format, in record order. Newer fields are appended
+/// to the end of the format over time, so older records simply have fewer of them.
+const FIELDS: [&str; 26] = [
+    "bucketOwner",
+    "bucket",
+    "eventTime",
+    "remoteIP",
+    "requester",
+    "requestID",
+    "operation",
+    "key",
+    "requestURI",
+    "httpStatus",
+    "errorCode",
+    "bytesSent",
+    "objectSize",
+    "totalTime",
+    "turnAroundTime",
+    "referer",
+    "userAgent",
+    "versionId",
+    "hostId",
+    "signatureVersion",
+    "cipherSuite",
+    "authenticationType",
+    "hostHeader",
+    "tlsVersion",
+    "accessPointArn",
+    "aclRequired",
+];
+
+/// Fields kept as JSON numbers so they can be summed and compared numerically.
+const NUMERIC_FIELDS: [&str; 5] = [
+    "httpStatus",
+    "bytesSent",
+    "objectSize",
+    "totalTime",
+    "turnAroundTime",
+];
+
+/// Split one access log line into its fields. Fields are separated by spaces, except that the
+/// time is wrapped in `[...]` and the request URI, referer and user agent in `"..."` (which
+/// may contain spaces and `\"`-escaped quotes).
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c == ' ' {
+            chars.next();
+            continue;
+        }
+        let mut field = String::new();
+        match c {
+            '[' => {
+                chars.next();
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    field.push(c);
+                }
+            }
+            '"' => {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                field.push(escaped);
+                            }
+                        }
+                        '"' => break,
+                        _ => field.push(c),
+                    }
+                }
+            }
+            _ => {
+                while let Some(&c) = chars.peek() {
+                    if c == ' ' {
+                        break;
+                    }
+                    field.push(c);
+                    chars.next();
+                }
+            }
+        }
+        fields.push(field);
+    }
+    fields
+}
+
+/// Parse one S3 server access log record into a JSON object keyed by `FIELDS`. The bracketed
+/// time (e.g. `06/Feb/2019:00:00:38 +0000`) becomes an RFC 3339 `eventTime`, and `-`
+/// placeholders are left out. Returns `None` for a line that is not an access log record.
+fn parse_line(line: &str) -> Option<Value> {
+    let fields = split_fields(line);
+    let time = DateTime::parse_from_str(fields.get(2)?, "%d/%b/%Y:%H:%M:%S %z").ok()?;
+    let mut map = Map::new();
+    for (name, value) in FIELDS.iter().zip(fields) {
+        if value == "-" {
+            continue;
+        }
+        let value = if *name == "eventTime" {
+            Value::String(
+                time.with_timezone(&Utc)
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+            )
+        } else {
+            match value.parse::<u64>() {
+                Ok(n) if NUMERIC_FIELDS.contains(name) => Value::from(n),
+                _ => Value::String(value),
+            }
+        };
+        map.insert(name.to_string(), value);
+    }
+    Some(Value::Object(map))
+}
+
+/// True if the first non-empty line of `contents` is an S3 server access log record.
+pub fn is_access_log(contents: &str) -> bool {
+    contents
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .is_some_and(|line| parse_line(line).is_some())
+}
+
+/// Stream the records of an S3 server access log file to `emit`, one JSON object per line. Lines
+/// that are not access log records (e.g. another file type in the same directory) are skipped.
+pub fn stream_s3_access_log(
+    reader: &mut dyn BufRead,
+    emit: &mut dyn FnMut(Value),
+) -> io::Result<()> {
+    let mut line = String::new();
+    while json_stream::read_line(reader, &mut line)? {
+        if let Some(record) = parse_line(line.trim_end_matches(['\r', '\n'])) {
+            emit(record);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a whole S3 server access log file with `stream_s3_access_log`.
+    fn parse_s3_access_log(contents: &str) -> Vec<Value> {
+        let mut events = Vec::new();
+        // Reading from a string cannot fail.
+        stream_s3_access_log(&mut contents.as_bytes(), &mut |v| events.push(v)).ok();
+        events
+    }
+
+    const RECORD: &str = r#"79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be amzn-s3-demo-bucket1 [06/Feb/2019:00:00:38 +0000] 192.0.2.3 79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be 3E57427F3EXAMPLE REST.GET.VERSIONING - "GET /amzn-s3-demo-bucket1?versioning HTTP/1.1" 200 - 113 - 7 - "-" "S3Console/0.4" - s9lzHYrFp76ZVxRcpX9+5cjAnEH2ROuNkd2BHfIa6UkFVdtjf5mKR3/eTPFvsiP/XV/VLi31234= SigV4 ECDHE-RSA-AES128-GCM-SHA256 AuthHeader amzn-s3-demo-bucket1.s3.us-west-1.amazonaws.com TLSV1.2 arn:aws:s3:us-west-1:123456789012:accesspoint/example-AP Yes"#;
+
+    #[test]
+    fn parses_documented_example_record() {
+        let events = parse_s3_access_log(RECORD);
+        assert_eq!(events.len(), 1);
+        let e = &events[0];
+        assert_eq!(e["bucket"], "amzn-s3-demo-bucket1");
+        assert_eq!(e["eventTime"], "2019-02-06T00:00:38Z");
+        assert_eq!(e["remoteIP"], "192.0.2.3");
+        assert_eq!(e["operation"], "REST.GET.VERSIONING");
+        assert_eq!(
+            e["requestURI"],
+            "GET /amzn-s3-demo-bucket1?versioning HTTP/1.1"
+        );
+        assert_eq!(e["httpStatus"], 200);
+        assert_eq!(e["bytesSent"], 113);
+        assert_eq!(e["userAgent"], "S3Console/0.4");
+        assert_eq!(e["aclRequired"], "Yes");
+        // `-` placeholders (key, error code, object size, referer) are left out.
+        assert!(e.get("key").is_none());
+        assert!(e.get("errorCode").is_none());
+        assert!(e.get("referer").is_none());
+    }
+
+    #[test]
+    fn quoted_fields_keep_spaces_and_escaped_quotes() {
+        let fields =
+            split_fields(r#"a [01/Jan/2024:00:00:00 +0900] "GET /k HTTP/1.1" "ua \"x\" y""#);
+        assert_eq!(
+            fields,
+            vec![
+                "a",
+                "01/Jan/2024:00:00:00 +0900",
+                "GET /k HTTP/1.1",
+                r#"ua "x" y"#
+            ]
+        );
+    }
+
+    #[test]
+    fn non_access_log_lines_are_skipped() {
+        assert!(parse_s3_access_log(r#"{"Records":[]}"#).is_empty());
+        assert!(parse_s3_access_log("version account-id interface-id srcaddr").is_empty());
+    }
+}
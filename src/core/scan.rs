@@ -1,10 +1,22 @@
+use crate::core::archive;
+use crate::core::azure_blob::{self, AzureBlobStore};
 use crate::core::color::SuzakuColor::{Green, Orange, Red};
-use crate::core::log_source::{LogSource, is_match_service};
+use crate::core::flow_log;
+use crate::core::json_stream;
+use crate::core::log_source::{LogSource, is_match_logsource};
+use crate::core::manifest;
+use crate::core::object_store::{self, ObjectStore, RemoteObject};
+use crate::core::s3::{self, S3Store};
+use crate::core::s3_access_log;
+use crate::core::scan_state::{ScanState, size_and_mtime};
+use crate::core::security_lake;
 use crate::core::summary::DetectionSummary;
-use crate::core::timeline_writer::{OutputContext, write_record};
-use crate::core::util::p;
-use crate::option::cli::{FileDateOption, TimeOption, TimelineOptions};
-use crate::option::timefiler::{filter_by_time, filter_file_by_date_path};
+use crate::core::timeline_writer::{OutputContext, event_time, write_record};
+use crate::core::util::{FileDigest, HashingReader, file_digest, is_stdin, p};
+use crate::option::cli::{FileDateOption, InputOption, TimeOption, TimelineOptions};
+use crate::option::timefiler::{
+    filter_by_time, filter_file_by_date_path, filter_prefix_by_date_path,
+};
 use bytesize::ByteSize;
 use chrono::{DateTime, Utc};
 use colored::Colorize;
@@ -17,16 +29,23 @@ use rayon::iter::ParallelIterator;
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator};
 use serde_json::Value;
 use sigma_rust::{CorrelationEngine, Event, Rule, TimestampedEvent, event_from_json};
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
 use std::fs::File;
-use std::io::{BufReader, Read};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender};
 use std::time::Duration;
-use std::{fs, io};
+use std::{fs, io, thread};
+
+/// Why a file skipped by `--state-file` is listed in the run manifest.
+const ALREADY_SCANNED: &str = "already scanned in an earlier run (--state-file)";
 
 #[allow(clippy::too_many_arguments)]
 pub fn scan_file<'a>(
-    f: &PathBuf,
+    f: &Path,
     context: &mut OutputContext<'a>,
     summary: &mut DetectionSummary,
     options: &TimelineOptions,
@@ -34,26 +53,53 @@ pub fn scan_file<'a>(
     matched_correlation: &mut Vec<TimestampedEvent<'a>>,
     correlation_engine: &'a CorrelationEngine,
     log: &LogSource,
+    mut state: Option<&mut ScanState>,
 ) {
-    let log_contents = get_content(f);
-    let events = if f.display().to_string().ends_with(".csv") {
-        parse_csv_events(&log_contents)
-    } else {
-        match load_json_from_file(&log_contents, log) {
-            Ok(value) => value,
-            Err(_e) => return,
-        }
+    let tracked = !is_stdin(f);
+    if tracked
+        && let Some(state) = state.as_deref_mut()
+        && state.is_scanned(f)
+    {
+        p(
+            Orange.rdg(context.config.no_color),
+            &format!("{} was already scanned (--state-file).", f.display()),
+            true,
+        );
+        manifest::record_skipped(&f.to_string_lossy(), ALREADY_SCANNED);
+        return;
+    }
+    let mut process_events = |events: &[Value]| {
+        detect_events(
+            events,
+            context,
+            summary,
+            options,
+            rules,
+            matched_correlation,
+            correlation_engine,
+        );
     };
-    let events = normalize_events(events, log);
-    detect_events(
-        &events,
-        context,
-        summary,
-        options,
-        rules,
-        matched_correlation,
-        correlation_engine,
+    let record_inputs = records_inputs(options);
+    let track = tracked && state.is_some();
+    // Taken before the file is opened, so bytes appended while it is scanned are noticed.
+    let before = if track { size_and_mtime(f).ok() } else { None };
+    let (result, digest) = stream_chunks_from_file(
+        f,
+        log,
+        &mut |events| process_events(&events),
+        &|_| {},
+        record_inputs || track,
     );
+    if let Err(e) = &result {
+        eprintln!("[WARNING] Stopped reading {}: {e}", f.display());
+        manifest::record_skipped(&f.to_string_lossy(), &format!("stopped reading: {e}"));
+    }
+    if track && let Some(state) = state {
+        mark_scanned(state, f, before, digest.as_ref(), result.is_ok());
+    }
+    if record_inputs {
+        manifest::record_input(&f.to_string_lossy(), digest);
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -66,6 +112,7 @@ pub fn scan_directory<'a>(
     matched_correlation: &mut Vec<TimestampedEvent<'a>>,
     correlation_engine: &'a CorrelationEngine,
     log: &LogSource,
+    state: Option<&mut ScanState>,
 ) {
     let no_color = context.config.no_color;
     let process_events = |events: &[Value]| {
@@ -86,6 +133,8 @@ pub fn scan_directory<'a>(
         no_color,
         log,
         &options.input_opt.file_date_opt,
+        state,
+        records_inputs(options),
     ) {
         p(
             Red.rdg(no_color),
@@ -95,17 +144,324 @@ pub fn scan_directory<'a>(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
+pub fn scan_remote<'a>(
+    context: &mut OutputContext<'a>,
+    summary: &mut DetectionSummary,
+    options: &TimelineOptions,
+    rules: &Vec<&Rule>,
+    matched_correlation: &mut Vec<TimestampedEvent<'a>>,
+    correlation_engine: &'a CorrelationEngine,
+    log: &LogSource,
+) {
+    let no_color = context.config.no_color;
+    let process_events = |events: &[Value]| {
+        detect_events(
+            events,
+            context,
+            summary,
+            options,
+            rules,
+            matched_correlation,
+            correlation_engine,
+        );
+    };
+    if let Err(e) = process_events_from_remote(
+        process_events,
+        &options.input_opt,
+        options.output_opt.output.is_some(),
+        no_color,
+        log,
+        records_inputs(options),
+    ) {
+        let url = remote_input_url(&options.input_opt).unwrap_or_default();
+        p(
+            Red.rdg(no_color),
+            &format!("Failed to scan {url}: {e}"),
+            true,
+        );
+    }
+}
+
+/// Scan the log files under `directory`. With `state`, the files it has already scanned are
+/// skipped and the rest recorded in it; with `record_inputs`, the files read are hashed and
+/// recorded for the run manifest.
+#[allow(clippy::too_many_arguments)]
 pub fn process_events_from_dir<F>(
-    mut process_events: F,
+    process_events: F,
     directory: &PathBuf,
     show_progress: bool,
     no_color: bool,
     log: &LogSource,
     file_date_opt: &FileDateOption,
+    mut state: Option<&mut ScanState>,
+    record_inputs: bool,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&[Value]),
+{
+    print_file_date_filter(file_date_opt, no_color);
+    let (_, mut file_paths, mut total_size) = count_files_recursive(directory, file_date_opt, log)?;
+    if let Some(state) = state.as_deref_mut() {
+        let found = file_paths.len();
+        file_paths.retain(|path| {
+            let scanned = state.is_scanned(path);
+            if scanned {
+                manifest::record_skipped(&path.to_string_lossy(), ALREADY_SCANNED);
+            }
+            !scanned
+        });
+        total_size = file_paths
+            .iter()
+            .map(|path| fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+            .sum();
+        p(
+            Green.rdg(no_color),
+            "Already scanned files skipped: ",
+            false,
+        );
+        p(
+            None,
+            &(found - file_paths.len()).to_formatted_string(&Locale::en),
+            true,
+        );
+    }
+    // Taken before any file is opened, so bytes appended while it is scanned are noticed.
+    let mut before = HashMap::new();
+    if state.is_some() {
+        for path in &file_paths {
+            if let Ok(size_and_mtime) = size_and_mtime(path) {
+                before.insert(path.clone(), size_and_mtime);
+            }
+        }
+    }
+    let hash = record_inputs || state.is_some();
+    let files: Vec<InputFile> = file_paths.into_iter().map(InputFile::Local).collect();
+    process_input_files(
+        process_events,
+        &files,
+        total_size,
+        show_progress,
+        no_color,
+        log,
+        hash,
+        |file, read_to_end, digest| {
+            let InputFile::Local(path) = file else {
+                return;
+            };
+            if let Some(state) = state.as_deref_mut() {
+                let before = before.get(path).copied();
+                mark_scanned(state, path, before, digest.as_ref(), read_to_end);
+            }
+            if record_inputs {
+                manifest::record_input(&path.to_string_lossy(), digest);
+            }
+        },
+    );
+    Ok(())
+}
+
+/// For `--follow`: scan the files under `d` that are not in `state` yet, and record them in it.
+/// A file is only scanned once its size is the same as at the previous call (`sizes`), so one
+/// still being written is not read half way. Returns the number of files scanned.
+#[allow(clippy::too_many_arguments)]
+pub fn scan_new_files<'a>(
+    d: &PathBuf,
+    context: &mut OutputContext<'a>,
+    summary: &mut DetectionSummary,
+    options: &TimelineOptions,
+    rules: &Vec<&Rule>,
+    matched_correlation: &mut Vec<TimestampedEvent<'a>>,
+    correlation_engine: &'a CorrelationEngine,
+    log: &LogSource,
+    state: &mut ScanState,
+    sizes: &mut HashMap<PathBuf, u64>,
+) -> usize {
+    let file_paths = match count_files_recursive(d, &options.input_opt.file_date_opt, log) {
+        Ok((_, file_paths, _)) => file_paths,
+        Err(e) => {
+            eprintln!("[WARNING] Cannot list directory {}: {e}", d.display());
+            return 0;
+        }
+    };
+    let mut ready = Vec::new();
+    for path in file_paths {
+        if state.is_scanned(&path) {
+            sizes.remove(&path);
+            continue;
+        }
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if sizes.insert(path.clone(), size) == Some(size) {
+            ready.push(path);
+        }
+    }
+    for path in &ready {
+        sizes.remove(path);
+        scan_file(
+            path,
+            context,
+            summary,
+            options,
+            rules,
+            matched_correlation,
+            correlation_engine,
+            log,
+            Some(state),
+        );
+    }
+    ready.len()
+}
+
+/// Record a scanned file in the `--state-file` state, as read to the end or as failed part-way,
+/// from its size and mtime `before` the scan and the `digest` of the bytes the scan read. It is
+/// left out, with a warning, if either is missing or it changed while it was read; it is then
+/// scanned again next time.
+fn mark_scanned(
+    state: &mut ScanState,
+    path: &Path,
+    before: Option<(u64, u128)>,
+    digest: Option<&FileDigest>,
+    read_to_end: bool,
+) {
+    let (Some(before), Some(digest)) = (before, digest) else {
+        eprintln!(
+            "[WARNING] Cannot hash {}, so it is not recorded in the state file.",
+            path.display()
+        );
+        return;
+    };
+    let recorded = if read_to_end {
+        state.mark_scanned(path, before, digest)
+    } else {
+        state.mark_failed(path, before, digest)
+    };
+    if !recorded {
+        eprintln!(
+            "[WARNING] {} changed while it was scanned, so it will be scanned again next time.",
+            path.display()
+        );
+    }
+}
+
+/// Whether a timeline run hashes and records its input files for the run manifest: only when it
+/// writes an `--output` file, and not with `--follow`, which runs until it is interrupted and
+/// so never writes one.
+pub fn records_inputs(options: &TimelineOptions) -> bool {
+    options.output_opt.output.is_some() && !options.follow
+}
+
+/// The `--s3` or `--azure-blob` URL, if the input is remote. Any SAS token is left out, so the
+/// URL can be shown in messages.
+pub fn remote_input_url(input_opt: &InputOption) -> Option<&str> {
+    let url = input_opt
+        .s3_url
+        .as_deref()
+        .or(input_opt.azure_blob_url.as_deref())?;
+    url.split('?').next()
+}
+
+/// `process_events_from_dir` for the objects under the `--s3` URL or the blobs under the
+/// `--azure-blob` container URL, which are listed and streamed through the storage API instead
+/// of being copied to disk first. With `--file-date-from`/`--file-date-to`, the date prefixes
+/// outside the range are not listed. With `record_inputs`, the objects read are hashed and
+/// recorded for the run manifest.
+pub fn process_events_from_remote<F>(
+    process_events: F,
+    input_opt: &InputOption,
+    show_progress: bool,
+    no_color: bool,
+    log: &LogSource,
+    record_inputs: bool,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&[Value]),
+{
+    let (store, prefix) = open_remote(input_opt)?;
+    process_events_from_store(
+        process_events,
+        store.as_ref(),
+        &prefix,
+        show_progress,
+        no_color,
+        log,
+        &input_opt.file_date_opt,
+        record_inputs,
+    )
+}
+
+/// The store and key prefix of the `--s3` or `--azure-blob` URL.
+fn open_remote(input_opt: &InputOption) -> Result<(Box<dyn ObjectStore>, String), Box<dyn Error>> {
+    match (&input_opt.s3_url, &input_opt.azure_blob_url) {
+        (Some(url), _) => {
+            let (bucket, prefix) = s3::parse_s3_url(url)?;
+            Ok((Box::new(S3Store::new(&bucket, &input_opt.s3_opt)?), prefix))
+        }
+        (None, Some(url)) => {
+            let container = azure_blob::parse_container_url(url)?;
+            let store = AzureBlobStore::new(&container, &input_opt.azure_blob_opt)?;
+            Ok((Box::new(store), container.prefix))
+        }
+        (None, None) => Err("No --s3 or --azure-blob URL was given".into()),
+    }
+}
+
+/// The log objects under `prefix` that are scanned for `log`. With
+/// `--file-date-from`/`--file-date-to`, the date prefixes outside the range are not listed.
+fn list_log_objects(
+    store: &dyn ObjectStore,
+    prefix: &str,
+    log: &LogSource,
+    file_date_opt: &FileDateOption,
+) -> io::Result<Vec<RemoteObject>> {
+    let prune = file_date_opt.file_date_from.is_some() || file_date_opt.file_date_to.is_some();
+    object_store::list_objects(
+        store,
+        prefix,
+        prune,
+        &|prefix| filter_prefix_by_date_path(file_date_opt, prefix),
+        &|url| is_log_file(Path::new(url), log) && filter_file_by_date_path(file_date_opt, url),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_events_from_store<F>(
+    process_events: F,
+    store: &dyn ObjectStore,
+    prefix: &str,
+    show_progress: bool,
+    no_color: bool,
+    log: &LogSource,
+    file_date_opt: &FileDateOption,
+    record_inputs: bool,
 ) -> Result<(), Box<dyn Error>>
 where
     F: FnMut(&[Value]),
 {
+    print_file_date_filter(file_date_opt, no_color);
+    let objects = list_log_objects(store, prefix, log, file_date_opt)?;
+    let total_size = objects.iter().map(|o| o.size).sum();
+    let files: Vec<InputFile> = objects
+        .into_iter()
+        .map(|o| InputFile::Remote(store, o))
+        .collect();
+    process_input_files(
+        process_events,
+        &files,
+        total_size,
+        show_progress,
+        no_color,
+        log,
+        record_inputs,
+        |file, _, digest| {
+            if record_inputs {
+                manifest::record_input(&file.name(), digest);
+            }
+        },
+    );
+    Ok(())
+}
+
+fn print_file_date_filter(file_date_opt: &FileDateOption, no_color: bool) {
     if file_date_opt.file_date_from.is_some() || file_date_opt.file_date_to.is_some() {
         let from_str = file_date_opt
             .file_date_from
@@ -127,7 +483,25 @@ where
         );
         println!();
     }
-    let (count, file_paths, total_size) = count_files_recursive(directory, file_date_opt)?;
+}
+
+/// Print the totals, then read `files` and pass their events to `process_events`, with a
+/// progress bar if `show_progress`. `on_finished` is called for each file once it is done, with
+/// whether it was read to the end and, if `hash`, the digest of the bytes read.
+#[allow(clippy::too_many_arguments)]
+fn process_input_files<F>(
+    mut process_events: F,
+    files: &[InputFile],
+    total_size: u64,
+    show_progress: bool,
+    no_color: bool,
+    log: &LogSource,
+    hash: bool,
+    mut on_finished: impl FnMut(&InputFile, bool, Option<FileDigest>),
+) where
+    F: FnMut(&[Value]),
+{
+    let count = files.len();
     let size = ByteSize::b(total_size).display().to_string();
 
     p(Green.rdg(no_color), "Total log files: ", false);
@@ -160,65 +534,39 @@ where
         pb.enable_steady_tick(Duration::from_millis(300));
     }
 
-    for path in file_paths {
-        // `path` is the real `PathBuf`, so files with non-UTF-8 names still resolve and are read.
-        // Render lossily only for the extension checks (extensions are ASCII) and the progress
-        // display.
-        let path_str = path.to_string_lossy();
-        if show_progress {
-            // The file may have been removed mid-scan; fall back to 0 rather than panicking.
-            let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
-            let size = ByteSize::b(size).display().to_string();
-            let pb_msg = format!("{path_str} ({size})");
-            pb.set_message(pb_msg);
-        }
-        let log_contents = if path_str.ends_with("json")
-            || path_str.ends_with("jsonl")
-            || path_str.ends_with("csv")
-        {
-            match fs::read_to_string(&path) {
-                Ok(contents) => contents,
-                Err(e) => {
-                    // The file was counted but could not be read (permissions,
-                    // non-UTF-8 content, removed mid-scan). Warn instead of
-                    // silently skipping, so the run's coverage is not overstated.
-                    eprintln!("[WARNING] Skipping {path_str}: {e}");
-                    if show_progress {
-                        pb.inc(1);
-                    }
-                    continue;
-                }
+    let mut failed = false;
+    let mut digest = None;
+    read_files_in_parallel(files, log, hash, |file, message| match message {
+        FileMessage::Started => {
+            failed = false;
+            digest = None;
+            if show_progress {
+                let size = ByteSize::b(file.size()).display().to_string();
+                pb.set_message(format!("{} ({size})", file.name()));
             }
-        } else if path_str.ends_with("gz") {
-            match read_gz_file(&path) {
-                Ok(contents) => contents,
-                Err(e) => {
-                    eprintln!("[WARNING] Skipping {path_str}: {e}");
-                    if show_progress {
-                        pb.inc(1);
-                    }
-                    continue;
-                }
+        }
+        FileMessage::Member(member) => {
+            if show_progress {
+                pb.set_message(member);
             }
-        } else {
+        }
+        FileMessage::Events(events) => process_events(&events),
+        FileMessage::Failed(e) => {
+            // The file was counted but could not be read to the end (permissions, corrupt or
+            // non-UTF-8 content, removed mid-scan, decompression bomb). Warn instead of silently
+            // skipping, so the run's coverage is not overstated.
+            eprintln!("[WARNING] Stopped reading {}: {e}", file.name());
+            manifest::record_skipped(&file.name(), &format!("stopped reading: {e}"));
+            failed = true;
+        }
+        FileMessage::Hashed(d) => digest = Some(d),
+        FileMessage::Finished => {
+            on_finished(file, !failed, digest.take());
             if show_progress {
                 pb.inc(1);
             }
-            continue;
-        };
-
-        let events = if path_str.ends_with("csv") {
-            parse_csv_events(&log_contents)
-        } else {
-            log_contents_to_events(&log_contents, log)
-        };
-        let events = normalize_events(events, log);
-        process_events(&events);
-
-        if show_progress {
-            pb.inc(1);
         }
-    }
+    });
     if show_progress {
         if no_color {
             pb.finish_with_message("Scanning finished.\n");
@@ -226,7 +574,168 @@ where
             pb.finish_with_message(style("Scanning finished.\n").color256(214).to_string());
         }
     }
-    Ok(())
+}
+
+/// Files read ahead of the one whose events are being processed, per reader thread.
+const FILES_IN_FLIGHT_PER_READER: usize = 2;
+
+/// Chunks a reader thread may buffer for one file before it waits for them to be processed.
+const CHUNKS_IN_FLIGHT_PER_FILE: usize = 2;
+
+/// One file to scan: on disk, or an object in remote storage.
+enum InputFile<'a> {
+    Local(PathBuf),
+    Remote(&'a dyn ObjectStore, RemoteObject),
+}
+
+impl InputFile<'_> {
+    /// The path or URL shown in progress messages and warnings. The real `PathBuf` is kept, so
+    /// local files with non-UTF-8 names still resolve; only the display is lossy.
+    fn name(&self) -> String {
+        match self {
+            InputFile::Local(path) => path.to_string_lossy().to_string(),
+            InputFile::Remote(store, object) => store.url(&object.key),
+        }
+    }
+
+    fn size(&self) -> u64 {
+        match self {
+            // The file may have been removed mid-scan; fall back to 0 rather than panicking.
+            InputFile::Local(path) => fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            InputFile::Remote(_, object) => object.size,
+        }
+    }
+
+    fn stream_chunks(
+        &self,
+        log: &LogSource,
+        process_events: &mut dyn FnMut(Vec<Value>),
+        on_member: &dyn Fn(&str),
+        hash: bool,
+    ) -> (Result<(), Box<dyn Error>>, Option<FileDigest>) {
+        match self {
+            InputFile::Local(path) => {
+                stream_chunks_from_file(path, log, process_events, on_member, hash)
+            }
+            InputFile::Remote(store, object) => {
+                stream_chunks_from_object(*store, object, log, process_events, on_member, hash)
+            }
+        }
+    }
+}
+
+/// What `read_files_in_parallel` reports for one file, in order.
+enum FileMessage {
+    /// Its events are about to be processed.
+    Started,
+    /// An archive member is being read.
+    Member(String),
+    /// A chunk of normalized events.
+    Events(Vec<Value>),
+    /// The file could not be read to the end.
+    Failed(String),
+    /// The digest of the file, if it was hashed and could be read to the end.
+    Hashed(FileDigest),
+    /// All of its events have been reported.
+    Finished,
+}
+
+/// Read, decompress and parse `files` on a pool of reader threads (one per rayon thread, so
+/// `--threads` applies), and pass each file's messages to `handle` on the calling thread in
+/// the order of `files`. Rule matching and output therefore see the events in the same order as
+/// when the files are read one by one, whichever file finishes first.
+///
+/// At most `FILES_IN_FLIGHT_PER_READER` files per reader are read ahead, each buffering at most
+/// `CHUNKS_IN_FLIGHT_PER_FILE` chunks, so memory use stays bounded. The readers are plain threads
+/// rather than rayon tasks, so a reader waiting on a full channel never holds up the rayon pool
+/// that `detect_events` matches rules on.
+fn read_files_in_parallel(
+    files: &[InputFile],
+    log: &LogSource,
+    hash: bool,
+    mut handle: impl FnMut(&InputFile, FileMessage),
+) {
+    let readers = rayon::current_num_threads().max(1);
+    let window = readers * FILES_IN_FLIGHT_PER_READER;
+    // Jobs are taken in order, so the file at the front of `pending` is always being read (or
+    // done) and draining it cannot deadlock.
+    let (job_tx, job_rx) = mpsc::sync_channel::<(&InputFile, SyncSender<FileMessage>)>(window);
+    let job_rx = Mutex::new(job_rx);
+    thread::scope(|scope| {
+        for _ in 0..readers {
+            scope.spawn(|| {
+                loop {
+                    // Release the lock before reading, so the other readers can take jobs.
+                    let job = job_rx.lock().unwrap().recv();
+                    let Ok((file, tx)) = job else {
+                        break;
+                    };
+                    // Sending only fails if the calling thread has stopped, e.g. on a panic.
+                    let (result, digest) = file.stream_chunks(
+                        log,
+                        &mut |events| tx.send(FileMessage::Events(events)).unwrap_or(()),
+                        &|member| {
+                            tx.send(FileMessage::Member(member.to_string()))
+                                .unwrap_or(())
+                        },
+                        hash,
+                    );
+                    if let Err(e) = result {
+                        tx.send(FileMessage::Failed(e.to_string())).unwrap_or(());
+                    }
+                    if let Some(digest) = digest {
+                        tx.send(FileMessage::Hashed(digest)).unwrap_or(());
+                    }
+                }
+            });
+        }
+        let mut files = files.iter();
+        let mut pending = VecDeque::with_capacity(window);
+        loop {
+            while pending.len() < window
+                && let Some(file) = files.next()
+            {
+                let (tx, rx) = mpsc::sync_channel(CHUNKS_IN_FLIGHT_PER_FILE);
+                // Never blocks: the queue holds at most `window` jobs.
+                job_tx.send((file, tx)).unwrap();
+                pending.push_back((file, rx));
+            }
+            let Some((file, rx)) = pending.pop_front() else {
+                break;
+            };
+            handle(file, FileMessage::Started);
+            // Ends when the reader is done with the file and drops its sender.
+            for message in rx {
+                handle(file, message);
+            }
+            handle(file, FileMessage::Finished);
+        }
+        drop(job_tx);
+    });
+}
+
+/// Normalize one raw CloudTrail record before rule matching.
+///
+/// The CloudTrail console's Event history download and `aws cloudtrail lookup-events` wrap each
+/// event in a row that carries the real record in a `CloudTrailEvent` field — a JSON string (CSV
+/// column or `lookup-events` output) or a nested object. Unwrap it so rules and the output
+/// profile match the actual record, the same way `normalize_azure_event` unwraps `AuditData`.
+/// OCSF records from Amazon Security Lake are mapped back to CloudTrail field names. Plain
+/// CloudTrail events are returned unchanged.
+fn normalize_aws_event(mut v: Value) -> Value {
+    if let Value::Object(map) = &v
+        && let Some(event) = map.get("CloudTrailEvent")
+    {
+        let inner = match event {
+            Value::String(s) => serde_json::from_str::<Value>(s).ok(),
+            Value::Object(_) => Some(event.clone()),
+            _ => None,
+        };
+        if let Some(inner) = inner {
+            v = inner;
+        }
+    }
+    security_lake::ocsf_to_cloudtrail(v)
 }
 
 /// Normalize one raw Azure/M365 record before rule matching.
@@ -313,77 +822,389 @@ fn normalize_azure_event(mut v: Value) -> Value {
     v
 }
 
-/// Apply `normalize_azure_event` to every event when scanning Azure logs.
-fn normalize_events(events: Vec<Value>, log: &LogSource) -> Vec<Value> {
-    match log {
-        LogSource::Azure => events.into_iter().map(normalize_azure_event).collect(),
-        _ => events,
+/// Normalize one Cloud Logging entry before rule matching.
+///
+/// Cloud Audit Logs carry the actual audit record in a `protoPayload` envelope
+/// (`type.googleapis.com/google.cloud.audit.AuditLog`) with camelCase field names, while
+/// SigmaHQ's gcp rules match the flattened `gcp.audit.*` names (e.g. `gcp.audit.method_name`).
+/// Add those aliases next to the original entry so both spellings are matchable. Entries without
+/// a `protoPayload` (application logs, `jsonPayload`/`textPayload`) are returned unchanged.
+fn normalize_gcp_event(mut v: Value) -> Value {
+    let Some(payload) = v.get("protoPayload").filter(|p| p.is_object()) else {
+        return v;
+    };
+    let mut audit = serde_json::Map::new();
+    for (alias, pointer) in [
+        ("method_name", "/methodName"),
+        ("service_name", "/serviceName"),
+        ("resource_name", "/resourceName"),
+        ("principal_email", "/authenticationInfo/principalEmail"),
+        ("caller_ip", "/requestMetadata/callerIp"),
+    ] {
+        if let Some(val) = payload.pointer(pointer) {
+            audit.insert(alias.to_string(), val.clone());
+        }
     }
+    if !audit.is_empty()
+        && let Value::Object(map) = &mut v
+    {
+        let mut gcp = serde_json::Map::new();
+        gcp.insert("audit".to_string(), Value::Object(audit));
+        map.insert("gcp".to_string(), Value::Object(gcp));
+    }
+    v
 }
 
-/// Parse a `Search-UnifiedAuditLog` CSV export into one JSON object per row.
-/// The real audit record is carried in the `AuditData` column and is unwrapped
-/// afterwards by `normalize_azure_event`.
-fn parse_csv_events(contents: &str) -> Vec<Value> {
-    let mut events = Vec::new();
-    let mut reader = csv::ReaderBuilder::new()
-        .flexible(true)
-        .from_reader(contents.as_bytes());
-    let headers = match reader.headers() {
-        Ok(h) => h.clone(),
-        Err(_) => return events,
+/// Normalize one Okta System Log event before rule matching.
+///
+/// The System Log API returns camelCase field names, while SigmaHQ's okta rules match lowercase
+/// ones (`eventtype`, `displaymessage`, `securitycontext.isproxy`). Add the lowercase aliases
+/// next to the original fields so both spellings are matchable.
+fn normalize_okta_event(mut v: Value) -> Value {
+    let Value::Object(map) = &mut v else {
+        return v;
     };
-    for record in reader.records().flatten() {
-        let mut map = serde_json::Map::new();
-        for (header, field) in headers.iter().zip(record.iter()) {
-            map.insert(header.to_string(), Value::String(field.to_string()));
+    for (alias, field) in [
+        ("eventtype", "eventType"),
+        ("displaymessage", "displayMessage"),
+    ] {
+        if let Some(val) = map.get(field).cloned() {
+            map.entry(alias).or_insert(val);
         }
-        events.push(Value::Object(map));
     }
-    events
+    if let Some(is_proxy) = map
+        .get("securityContext")
+        .and_then(|c| c.get("isProxy"))
+        .cloned()
+    {
+        let mut security_context = serde_json::Map::new();
+        security_context.insert("isproxy".to_string(), is_proxy);
+        map.entry("securitycontext")
+            .or_insert(Value::Object(security_context));
+    }
+    v
 }
 
-fn log_contents_to_events(log_contents: &str, log: &LogSource) -> Vec<Value> {
-    match log {
-        LogSource::Aws => {
-            // Try parsing the whole file as a single JSON document first.
-            if let Ok(json_value) = serde_json::from_str::<Value>(log_contents) {
-                return aws_records(json_value);
-            }
-            // Fall back to JSONL: one JSON document per line, each of which may be a single
-            // CloudTrail event or a `{ "Records": [...] }` batch.
-            log_contents
-                .lines()
-                .filter_map(|line| serde_json::from_str::<Value>(line).ok())
-                .flat_map(aws_records)
-                .collect()
+/// Split one Google Workspace Admin SDK Reports activity into one record per entry of its
+/// `events[]` array before rule matching.
+///
+/// Each record keeps the activity's `id`, `actor` and `ipAddress` and gets the event's `type` and
+/// `name` as `eventType`/`eventName`. The event's `parameters[]` name/value array is folded into a
+/// plain `parameters` object (as `normalize_azure_event` does for UAL `ExtendedProperties`) so
+/// rules can reach values like `parameters.login_type`. An activity without `events` is returned
+/// as a single record unchanged.
+fn normalize_gws_activity(v: Value) -> Vec<Value> {
+    let Value::Object(mut activity) = v else {
+        return vec![v];
+    };
+    let Some(Value::Array(events)) = activity.remove("events") else {
+        return vec![Value::Object(activity)];
+    };
+    events
+        .into_iter()
+        .map(|event| {
+            let mut record = activity.clone();
+            if let Some(val) = event.get("type") {
+                record.insert("eventType".to_string(), val.clone());
+            }
+            if let Some(val) = event.get("name") {
+                record.insert("eventName".to_string(), val.clone());
+            }
+            if let Some(Value::Array(params)) = event.get("parameters") {
+                record.insert(
+                    "parameters".to_string(),
+                    Value::Object(fold_gws_parameters(params)),
+                );
+            }
+            Value::Object(record)
+        })
+        .collect()
+}
+
+/// Fold a Reports API `parameters[]` array into a `{name: value}` object. A parameter carries
+/// its value in one of `value`, `intValue`, `boolValue`, `multiValue`, `multiIntValue`,
+/// `messageValue` or `multiMessageValue`; the message forms nest another `parameter[]` array.
+fn fold_gws_parameters(params: &[Value]) -> serde_json::Map<String, Value> {
+    let mut folded = serde_json::Map::new();
+    for param in params {
+        let Some(Value::String(name)) = param.get("name") else {
+            continue;
+        };
+        let val = if let Some(Value::Array(nested)) = param.pointer("/messageValue/parameter") {
+            Value::Object(fold_gws_parameters(nested))
+        } else if let Some(Value::Array(messages)) = param.get("multiMessageValue") {
+            Value::Array(
+                messages
+                    .iter()
+                    .filter_map(|m| m.get("parameter").and_then(Value::as_array))
+                    .map(|nested| Value::Object(fold_gws_parameters(nested)))
+                    .collect(),
+            )
+        } else {
+            [
+                "value",
+                "intValue",
+                "boolValue",
+                "multiValue",
+                "multiIntValue",
+            ]
+            .iter()
+            .find_map(|k| param.get(*k))
+            .cloned()
+            .unwrap_or(Value::Null)
+        };
+        folded.insert(name.clone(), val);
+    }
+    folded
+}
+
+/// Normalize one Kubernetes audit event before rule matching: expose the first entry of
+/// `sourceIPs` (the client address; later entries are proxies) as `sourceIP`, so the output
+/// profile and GeoIP lookup can use it like a scalar field.
+fn normalize_k8s_event(mut v: Value) -> Value {
+    let src_ip = v.pointer("/sourceIPs/0").cloned();
+    if let Some(src_ip) = src_ip
+        && let Value::Object(map) = &mut v
+    {
+        map.entry("sourceIP").or_insert(src_ip);
+    }
+    v
+}
+
+/// Apply the per-source normalization (`normalize_aws_event` / `normalize_azure_event` /
+/// `normalize_gcp_event` / `normalize_okta_event` / `normalize_gws_activity` /
+/// `normalize_k8s_event`) to every event before rule matching.
+fn normalize_events(events: Vec<Value>, log: &LogSource) -> Vec<Value> {
+    match log {
+        LogSource::Aws => events.into_iter().map(normalize_aws_event).collect(),
+        LogSource::Azure => events.into_iter().map(normalize_azure_event).collect(),
+        LogSource::Gcp => events.into_iter().map(normalize_gcp_event).collect(),
+        LogSource::Okta => events.into_iter().map(normalize_okta_event).collect(),
+        LogSource::Gws => events
+            .into_iter()
+            .flat_map(normalize_gws_activity)
+            .collect(),
+        LogSource::Kubernetes => events.into_iter().map(normalize_k8s_event).collect(),
+        LogSource::All => events.into_iter().flat_map(all_records).collect(),
+        _ => events,
+    }
+}
+
+/// The log source a record read by `cloud-timeline` (`LogSource::All`) comes from, judged by the
+/// fields that each source's records carry. `None` for records of no known source.
+fn detect_log_source(record: &Value) -> Option<LogSource> {
+    let has = |key: &str| record.get(key).is_some();
+    if (has("eventSource") && (has("eventVersion") || has("eventTime")))
+        || has("CloudTrailEvent")
+        || security_lake::is_ocsf_cloudtrail(record)
+    {
+        Some(LogSource::Aws)
+    } else if has("auditID")
+        || record["apiVersion"]
+            .as_str()
+            .is_some_and(|v| v.starts_with("audit.k8s.io/"))
+    {
+        Some(LogSource::Kubernetes)
+    } else if has("logName") || has("protoPayload") {
+        Some(LogSource::Gcp)
+    } else if has("eventType") && has("published") {
+        Some(LogSource::Okta)
+    } else if record.pointer("/id/applicationName").is_some()
+        || record["kind"]
+            .as_str()
+            .is_some_and(|k| k.starts_with("admin#reports#"))
+    {
+        Some(LogSource::Gws)
+    } else if has("Workload")
+        || has("RecordType")
+        || has("AuditData")
+        || has("operationName")
+        || has("riskEventType")
+        || (has("category") && (has("time") || has("eventTimestamp")))
+    {
+        Some(LogSource::Azure)
+    } else if has("interface-id") || has("srcaddr") {
+        Some(LogSource::VpcFlow)
+    } else if has("bucketOwner") && has("operation") {
+        Some(LogSource::S3Access)
+    } else {
+        None
+    }
+}
+
+/// Unpack and normalize one record read by `cloud-timeline` (`LogSource::All`) with the loader of
+/// the source it comes from (`detect_log_source`), so CloudTrail and Azure/M365 records (and
+/// those of the other sources) in the same directory, or even the same file, end up in one
+/// timeline. The CloudWatch Logs subscription envelope (CloudTrail, EKS) and AKS `kube-audit`
+/// diagnostic records wrap another source's records and are unwrapped first. Records of no
+/// known source are dropped.
+fn all_records(value: Value) -> Vec<Value> {
+    if let Value::Array(records) = value {
+        return records.into_iter().flat_map(all_records).collect();
+    }
+    // AKS diagnostic records would otherwise pass for Azure activity records.
+    let is_kube_audit = value["category"]
+        .as_str()
+        .is_some_and(|c| c.starts_with("kube-audit"));
+    if !is_kube_audit && let Some(log) = detect_log_source(&value) {
+        return normalize_events(source_records(value, &log), &log);
+    }
+    let wrapped: Vec<&str> = if is_kube_audit {
+        value
+            .pointer("/properties/log")
+            .and_then(Value::as_str)
+            .into_iter()
+            .collect()
+    } else if let Some(Value::Array(log_events)) =
+        value.get("logEvents").or_else(|| value.get("events"))
+    {
+        log_events
+            .iter()
+            .filter_map(|e| e.get("message").and_then(Value::as_str))
+            .collect()
+    } else {
+        vec![]
+    };
+    wrapped
+        .into_iter()
+        .filter_map(|m| serde_json::from_str::<Value>(m).ok())
+        .flat_map(all_records)
+        .collect()
+}
+
+/// Stream a CSV export as one JSON object per row: a `Search-UnifiedAuditLog` export, whose real
+/// audit record is carried in the `AuditData` column and is unwrapped afterwards by
+/// `normalize_azure_event`, or a CloudTrail Event history download, whose `CloudTrailEvent`
+/// column is unwrapped by `normalize_aws_event`.
+fn read_csv_records(reader: &mut dyn BufRead, emit: &mut dyn FnMut(Value)) -> io::Result<()> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let headers = match reader.headers() {
+        Ok(h) => h.clone(),
+        Err(_) => return Ok(()),
+    };
+    for record in reader.records().flatten() {
+        let mut map = serde_json::Map::new();
+        for (header, field) in headers.iter().zip(record.iter()) {
+            map.insert(header.to_string(), Value::String(field.to_string()));
         }
-        LogSource::Azure => {
-            // Try parsing the whole file as a single JSON document first.
-            if let Ok(json_value) = serde_json::from_str::<Value>(log_contents) {
-                return azure_records(json_value);
+        emit(Value::Object(map));
+    }
+    Ok(())
+}
+
+/// Keys of the batch envelopes (`{ "<key>": [...] }`) whose records are streamed one by one
+/// rather than parsing the whole batch first. Other envelopes are unpacked by `source_records`.
+fn envelope_keys(log: &LogSource) -> &'static [&'static str] {
+    match log {
+        LogSource::Aws => &["Records", "Events"],
+        LogSource::Azure => &["records", "value"],
+        LogSource::Gcp => &["entries"],
+        LogSource::Gws | LogSource::Kubernetes => &["items"],
+        LogSource::All => &["Records", "Events", "records", "value", "entries", "items"],
+        _ => &[],
+    }
+}
+
+/// Extract the individual records of `log` from one parsed JSON document.
+fn source_records(value: Value, log: &LogSource) -> Vec<Value> {
+    match log {
+        LogSource::Aws => aws_records(value),
+        LogSource::Azure => azure_records(value),
+        LogSource::Gcp => gcp_records(value),
+        LogSource::Okta => okta_records(value),
+        LogSource::Gws => gws_records(value),
+        LogSource::Kubernetes => k8s_records(value),
+        // `LogSource::All` records are told apart and unpacked by `normalize_events`.
+        _ => vec![value],
+    }
+}
+
+/// Stream the records of one log file (or archive member) at `path` from `reader` to `emit`: a
+/// CSV export row by row, anything else in `log`'s JSON/text format. JSON is read as a single
+/// document (whose batch envelope is streamed record by record), or as JSONL, one JSON document
+/// per line, each of which may itself be a batch or be timestamp-prefixed by a CloudWatch Logs
+/// export.
+fn read_records(
+    reader: &mut dyn BufRead,
+    path: &str,
+    log: &LogSource,
+    emit: &mut dyn FnMut(Value),
+) -> io::Result<()> {
+    json_stream::skip_bom(reader)?;
+    if path.ends_with("csv") {
+        return read_csv_records(reader, emit);
+    }
+    match log {
+        // `cloud-timeline` tells the text formats apart by their first line.
+        LogSource::All => {
+            let head = String::from_utf8_lossy(reader.fill_buf()?).into_owned();
+            if flow_log::is_flow_log(&head) {
+                flow_log::stream_flow_log(reader, emit)
+            } else if s3_access_log::is_access_log(&head) {
+                s3_access_log::stream_s3_access_log(reader, emit)
+            } else {
+                json_stream::stream_json(reader, envelope_keys(log), emit)
             }
-            // Fall back to JSONL: one JSON document per line, each of which may
-            // itself be a `{ "records": [...] }` batch (Event Hub capture).
-            log_contents
-                .lines()
-                .filter_map(|line| serde_json::from_str::<Value>(line).ok())
-                .flat_map(azure_records)
-                .collect()
         }
-        _ => vec![],
+        // Space-delimited text, not JSON; other files in the directory yield no events.
+        LogSource::VpcFlow => flow_log::stream_flow_log(reader, emit),
+        // One access log record per line; lines that do not parse are skipped.
+        LogSource::S3Access => s3_access_log::stream_s3_access_log(reader, emit),
+        _ => json_stream::stream_json(reader, envelope_keys(log), &mut |value| {
+            source_records(value, log).into_iter().for_each(&mut *emit)
+        }),
+    }
+}
+
+/// Collects streamed records and hands them to `process_events` in normalized chunks of at most
+/// `EVENT_CHUNK_SIZE` events, so memory use does not grow with the size of the input.
+struct EventChunker<'a> {
+    log: &'a LogSource,
+    records: Vec<Value>,
+    process_events: &'a mut dyn FnMut(Vec<Value>),
+}
+
+impl EventChunker<'_> {
+    fn push(&mut self, record: Value) {
+        self.records.push(record);
+        if self.records.len() >= EVENT_CHUNK_SIZE {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.records.is_empty() {
+            return;
+        }
+        let events = normalize_events(std::mem::take(&mut self.records), self.log);
+        (self.process_events)(events);
     }
 }
 
 /// Extract the individual CloudTrail events from one parsed JSON document. Handles the shapes
-/// seen across CloudTrail exports: the standard `{ "Records": [...] }` delivery batch, a bare
-/// array of events, or a single event object (e.g. one JSONL line).
+/// seen across CloudTrail exports: the standard `{ "Records": [...] }` delivery batch, the
+/// `lookup-events` `{ "Events": [...] }` response, the CloudWatch Logs subscription /
+/// `filter-log-events` envelope (`{ "logEvents"|"events": [{ "message": "<event json>" }] }`),
+/// a bare array of events, or a single event object (e.g. one JSONL line). `CloudTrailEvent`
+/// wrappers and Security Lake OCSF records are turned into CloudTrail events afterwards by
+/// `normalize_aws_event`, in `normalize_events`.
 fn aws_records(value: Value) -> Vec<Value> {
     match value {
         Value::Array(records) => records,
         Value::Object(mut map) => {
             if let Some(Value::Array(records)) = map.remove("Records") {
                 records
+            } else if let Some(Value::Array(records)) = map.remove("Events") {
+                records
+            } else if let Some(Value::Array(log_events)) =
+                map.remove("logEvents").or_else(|| map.remove("events"))
+            {
+                // `CONTROL_MESSAGE` batches carry a non-JSON message and yield nothing.
+                log_events
+                    .iter()
+                    .filter_map(|e| e.get("message").and_then(Value::as_str))
+                    .filter_map(|m| serde_json::from_str::<Value>(m).ok())
+                    .collect()
             } else {
                 vec![Value::Object(map)]
             }
@@ -412,6 +1233,88 @@ fn azure_records(value: Value) -> Vec<Value> {
     }
 }
 
+/// Extract the individual Cloud Logging entries from one parsed JSON document. Handles the
+/// shapes seen across GCP exports: a bare array of `LogEntry` objects (`gcloud logging read
+/// --format=json`), the Logging API `{ "entries": [...] }` response, or a single entry (one line
+/// of a GCS sink's JSONL file).
+fn gcp_records(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(records) => records,
+        Value::Object(mut map) => {
+            if let Some(Value::Array(records)) = map.remove("entries") {
+                records
+            } else {
+                vec![Value::Object(map)]
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// Extract the individual Okta System Log events from one parsed JSON document. The
+/// `/api/v1/logs` API returns a bare array of events; a JSONL export has one event per line.
+fn okta_records(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(records) => records,
+        Value::Object(map) => vec![Value::Object(map)],
+        _ => vec![],
+    }
+}
+
+/// Extract the individual Google Workspace activities from one parsed JSON document. Handles the
+/// Reports API `activities.list` response `{ "kind": "admin#reports#activities", "items": [...] }`,
+/// a bare array of activities, or a single activity (one JSONL line).
+fn gws_records(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(records) => records,
+        Value::Object(mut map) => {
+            if let Some(Value::Array(records)) = map.remove("items") {
+                records
+            } else if map.contains_key("kind") && !map.contains_key("id") {
+                // An `activities.list` page with no matching activities has no `items`.
+                vec![]
+            } else {
+                vec![Value::Object(map)]
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// Extract the individual Kubernetes audit events from one parsed JSON document. Handles a bare
+/// `audit.k8s.io/v1` `Event` (one JSONL line), an `EventList` (`{ "items": [...] }`), a bare
+/// array, and the wrappers EKS and AKS put around the events: the CloudWatch Logs subscription /
+/// `filter-log-events` envelope (`{ "logEvents"|"events": [{ "message": "<event json>" }] }`)
+/// and the Azure diagnostic-settings batch (`{ "records": [{ "properties": { "log": "<event
+/// json>" } }] }`).
+fn k8s_records(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(records) => records.into_iter().flat_map(k8s_records).collect(),
+        Value::Object(mut map) => {
+            if let Some(Value::Array(records)) = map.remove("items") {
+                records
+            } else if let Some(Value::Array(log_events)) =
+                map.remove("logEvents").or_else(|| map.remove("events"))
+            {
+                log_events
+                    .iter()
+                    .filter_map(|e| e.get("message").and_then(Value::as_str))
+                    .filter_map(|m| serde_json::from_str::<Value>(m).ok())
+                    .collect()
+            } else if let Some(Value::Array(records)) = map.remove("records") {
+                records
+                    .iter()
+                    .filter_map(|r| r.pointer("/properties/log").and_then(Value::as_str))
+                    .filter_map(|m| serde_json::from_str::<Value>(m).ok())
+                    .collect()
+            } else {
+                vec![Value::Object(map)]
+            }
+        }
+        _ => vec![],
+    }
+}
+
 fn detect_events<'a>(
     events: &[Value],
     context: &mut OutputContext<'a>,
@@ -424,6 +1327,17 @@ fn detect_events<'a>(
     // If all the events are loaded at once, it can consume too much memory.
     // To avoid the problem, we split the events into chunks.
     const CHUNK_SIZE: usize = 1000;
+    let events: Vec<&Value> = match summary.seen_events.as_mut() {
+        Some(seen) => {
+            let kept: Vec<&Value> = events
+                .iter()
+                .filter(|event| !seen.is_duplicate(event))
+                .collect();
+            summary.duplicate_events += events.len() - kept.len();
+            kept
+        }
+        None => events.iter().collect(),
+    };
     let ts_key = context
         .prof_ts_key
         .strip_prefix(".")
@@ -439,7 +1353,7 @@ fn detect_events<'a>(
             .zip(repeated_time_opt.into_par_iter())
             .filter_map(|(event, time_opt)| {
                 if filter_by_time(time_opt, event, ts_key) {
-                    Some(event)
+                    Some(*event)
                 } else {
                     None
                 }
@@ -456,8 +1370,7 @@ fn detect_events<'a>(
                 let matched_rules: Vec<&Rule> = rules
                     .par_iter()
                     .filter(move |rule| {
-                        rule.is_match(json_event)
-                            && is_match_service(&rule.logsource.service, json_event)
+                        rule.is_match(json_event) && is_match_logsource(rule, json_event)
                     })
                     .map(|rule| *rule)
                     .collect();
@@ -489,6 +1402,40 @@ fn detect_events<'a>(
     }
 }
 
+/// Field added to the events kept for correlation, holding the sequence number they were given
+/// when matched. Neither rules nor output profiles refer to it.
+const CORRELATION_SEQ_FIELD: &str = "SuzakuCorrelationSeq";
+
+/// The sequence number the next event kept for correlation is given.
+static NEXT_CORRELATION_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// The sequence number the next event kept for correlation is given. `--follow` takes it before
+/// scanning new files, so the events matched in those files are the ones numbered from it.
+pub fn next_correlation_seq() -> u64 {
+    NEXT_CORRELATION_SEQ.load(Ordering::Relaxed)
+}
+
+/// The sequence number an event kept for correlation was given when it was matched.
+pub fn correlation_seq(event: &TimestampedEvent) -> Option<u64> {
+    event
+        .event
+        .get(CORRELATION_SEQ_FIELD)?
+        .value_to_string()
+        .parse()
+        .ok()
+}
+
+/// `event` with the next correlation sequence number added as [`CORRELATION_SEQ_FIELD`].
+fn with_correlation_seq(json: &Value, event: &Event) -> Event {
+    let mut json = json.clone();
+    let Some(map) = json.as_object_mut() else {
+        return event.clone();
+    };
+    let seq = NEXT_CORRELATION_SEQ.fetch_add(1, Ordering::Relaxed);
+    map.insert(CORRELATION_SEQ_FIELD.to_string(), Value::from(seq));
+    event_from_json(&json.to_string()).unwrap_or_else(|_| event.clone())
+}
+
 fn process_correlation_base_rule<'a>(
     engine: &'a CorrelationEngine,
     json_events: Vec<(&Value, Event)>,
@@ -496,23 +1443,21 @@ fn process_correlation_base_rule<'a>(
 ) -> Vec<TimestampedEvent<'a>> {
     json_events
         .par_iter()
-        .flat_map(|(_, event)| {
+        .flat_map(|(json, event)| {
             engine
                 .base_rules
                 .values()
                 .filter_map(|rule| {
                     if rule.is_match(event)
-                        && let Some(timestamp_field) = event.get(context.prof_ts_key)
+                        && let Some(ts) = event_time(event, context.prof_ts_key)
+                        && let Ok(parsed_time) = DateTime::parse_from_rfc3339(&ts)
                     {
-                        let ts = timestamp_field.value_to_string();
-                        if let Ok(parsed_time) = DateTime::parse_from_rfc3339(&ts) {
-                            let utc_time = parsed_time.with_timezone(&Utc);
-                            return Some(TimestampedEvent {
-                                event: event.clone(),
-                                timestamp: utc_time,
-                                rule,
-                            });
-                        }
+                        let utc_time = parsed_time.with_timezone(&Utc);
+                        return Some(TimestampedEvent {
+                            event: with_correlation_seq(json, event),
+                            timestamp: utc_time,
+                            rule,
+                        });
                     }
                     None
                 })
@@ -549,31 +1494,29 @@ pub fn append_summary_data(
                 .or_insert(1);
         }
     }
-    if let Some(event_time) = event.get(context.prof_ts_key) {
-        let event_time_str = event_time.value_to_string();
-        if let Ok(event_time) = event_time_str.parse::<DateTime<Utc>>() {
-            let unix_time = event_time.timestamp();
-            summary.timestamps.push(unix_time);
-            if summary.first_event_time.is_none() || event_time < summary.first_event_time.unwrap()
-            {
-                summary.first_event_time = Some(event_time);
-            }
-            if summary.last_event_time.is_none() || event_time > summary.last_event_time.unwrap() {
-                summary.last_event_time = Some(event_time);
-            }
-            if let Some(level) = &rule.level
-                && generate
-            {
-                let level = format!("{level:?}").to_lowercase();
-                let date = event_time.date_naive().format("%Y-%m-%d").to_string();
-                summary
-                    .dates_with_hits
-                    .entry(level)
-                    .or_default()
-                    .entry(date)
-                    .and_modify(|e| *e += 1)
-                    .or_insert(1);
-            }
+    if let Some(event_time_str) = event_time(event, context.prof_ts_key)
+        && let Ok(event_time) = event_time_str.parse::<DateTime<Utc>>()
+    {
+        let unix_time = event_time.timestamp();
+        summary.timestamps.push(unix_time);
+        if summary.first_event_time.is_none() || event_time < summary.first_event_time.unwrap() {
+            summary.first_event_time = Some(event_time);
+        }
+        if summary.last_event_time.is_none() || event_time > summary.last_event_time.unwrap() {
+            summary.last_event_time = Some(event_time);
+        }
+        if let Some(level) = &rule.level
+            && generate
+        {
+            let level = format!("{level:?}").to_lowercase();
+            let date = event_time.date_naive().format("%Y-%m-%d").to_string();
+            summary
+                .dates_with_hits
+                .entry(level)
+                .or_default()
+                .entry(date)
+                .and_modify(|e| *e += 1)
+                .or_insert(1);
         }
     }
 }
@@ -582,25 +1525,56 @@ pub fn append_summary_data(
 fn format_date_display(s: &str) -> String {
     if s.len() == 8 {
         format!("{}-{}-{}", &s[0..4], &s[4..6], &s[6..8])
+    } else if s.len() == 10 {
+        format!("{}-{}-{} {}:00", &s[0..4], &s[4..6], &s[6..8], &s[8..10])
     } else {
         s.to_string()
     }
 }
 
+/// True if `path` has an extension `process_events_from_dir` reads for `log`. VPC Flow Logs and
+/// S3 server access logs are plain text, so `.log`/`.txt` files are read for those sources too,
+/// as are the extension-less objects S3 writes access logs to. CloudTrail also accepts the
+/// `.parquet` files Amazon Security Lake writes. `cloud-timeline` (`LogSource::All`) reads all of
+/// these except extension-less files. Archives (`.zip`, `.tar`, `.tgz`, `.zst`, ...)
+/// are accepted for every source and their members are filtered with `is_log_member`.
+fn is_log_file(path: &Path, log: &LogSource) -> bool {
+    match path.extension().and_then(|s| s.to_str()) {
+        Some("json" | "jsonl" | "gz" | "csv") => true,
+        Some("log" | "txt") => matches!(
+            log,
+            LogSource::VpcFlow | LogSource::S3Access | LogSource::All
+        ),
+        Some("parquet") => matches!(log, LogSource::Aws | LogSource::All),
+        Some("zip" | "tar" | "tgz" | "tzst" | "zst" | "zstd") => true,
+        None => matches!(log, LogSource::S3Access),
+        _ => false,
+    }
+}
+
+/// True if an archive member named `name` is read as a log file for `log`. Parquet files need
+/// random access and are only read from disk.
+fn is_log_member(name: &str, log: &LogSource) -> bool {
+    !name.ends_with(".parquet") && is_log_file(Path::new(name), log)
+}
+
 fn count_files_recursive(
     directory: &PathBuf,
     file_date_opt: &FileDateOption,
+    log: &LogSource,
 ) -> Result<(usize, Vec<PathBuf>, u64), Box<dyn Error>> {
     let mut count = 0;
     let mut paths = Vec::new();
     let mut total_size = 0;
-    for entry in fs::read_dir(directory)? {
-        let entry = entry?;
-        let path = entry.path();
+    // `read_dir` order depends on the filesystem; sort so files are scanned (and their detections
+    // written) in the same order on every run.
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
         if path.is_file() {
-            if let Some(ext) = path.extension().and_then(|s| s.to_str())
-                && (ext == "json" || ext == "jsonl" || ext == "gz" || ext == "csv")
-            {
+            if is_log_file(&path, log) {
                 // The date filter matches on the path string; filenames need not be valid UTF-8
                 // (e.g. on Linux), so render lossily *only for the filter*. The real `PathBuf` is
                 // stored so a non-UTF-8 name still resolves when the file is read later.
@@ -612,7 +1586,8 @@ fn count_files_recursive(
                 paths.push(path);
             }
         } else if path.is_dir() {
-            let (sub_count, sub_paths, sub_size) = count_files_recursive(&path, file_date_opt)?;
+            let (sub_count, sub_paths, sub_size) =
+                count_files_recursive(&path, file_date_opt, log)?;
             count += sub_count;
             total_size += sub_size;
             paths.extend(sub_paths);
@@ -621,121 +1596,268 @@ fn count_files_recursive(
     Ok((count, paths, total_size))
 }
 
-/// Upper bound on the decompressed size of a single `.gz` input. DEFLATE can inflate at
-/// roughly 1032:1, so a few-MB archive can otherwise expand to many GB and OOM-kill the
-/// whole scan. Generous enough for real logs, finite enough to stop a decompression bomb.
-const MAX_DECOMPRESSED_BYTES: u64 = 3 * 1024 * 1024 * 1024; // 3 GiB
+/// Records handed to `process_events` at a time. Files are streamed, so this (rather than the
+/// size of the largest file) bounds how many events are held in memory.
+const EVENT_CHUNK_SIZE: usize = 1000;
 
-pub fn read_gz_file(file_path: &PathBuf) -> io::Result<String> {
-    read_gz_file_capped(file_path, MAX_DECOMPRESSED_BYTES)
-}
+/// Upper bound on the decompressed size of a single `.gz` input or archive. DEFLATE can inflate
+/// at roughly 1032:1, so a few-MB archive can otherwise expand to many GB and keep a scan busy
+/// for hours. Generous enough for real logs, finite enough to stop a decompression bomb.
+const MAX_DECOMPRESSED_BYTES: u64 = 3 * 1024 * 1024 * 1024; // 3 GiB
 
-/// Decompresses a gzip file, refusing to buffer more than `max_bytes` of decompressed data.
+/// A decompressing reader that fails once more than `max_bytes` have been read.
 ///
 /// `Read::take` alone is not enough: it truncates silently and returns `Ok`, which would feed
-/// a partial/corrupted log to the parser. Instead we read one byte past the ceiling and treat
-/// hitting it as an error, so the caller's per-file handling (`Err(_) => continue` /
-/// `unwrap_or_default()`) skips just that file and the scan continues.
-fn read_gz_file_capped(file_path: &PathBuf, max_bytes: u64) -> io::Result<String> {
-    let file = File::open(file_path)?;
-    let decoder = GzDecoder::new(BufReader::new(file));
-    let mut buf = Vec::new();
-    decoder.take(max_bytes + 1).read_to_end(&mut buf)?;
-    if buf.len() as u64 > max_bytes {
-        // The descriptive message is carried in the error so the caller's
-        // single "[WARNING] Skipping <file>: <err>" line reports the reason.
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!(
-                "decompressed size exceeds the {} GiB limit (possible gzip bomb)",
-                max_bytes / (1024 * 1024 * 1024)
-            ),
-        ));
+/// a partial/corrupted log to the parser. Instead, going past the ceiling is an error, so the
+/// caller's per-file handling reports it and the scan continues with the next file.
+struct CappedReader<R> {
+    inner: R,
+    remaining: u64,
+    max_bytes: u64,
+}
+
+impl<R: Read> Read for CappedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n as u64 > self.remaining {
+            // The descriptive message is carried in the error so the caller's
+            // single "[WARNING] ... <file>: <err>" line reports the reason.
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "decompressed size exceeds the {} GiB limit (possible gzip bomb)",
+                    self.max_bytes / (1024 * 1024 * 1024)
+                ),
+            ));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Wrap `reader` in a gzip decoder if its contents start with the gzip magic bytes, refusing to
+/// decompress more than `max_bytes`. Anything else is read as is.
+fn gzip_aware_reader<'a, R: Read + 'a>(
+    reader: R,
+    max_bytes: u64,
+) -> io::Result<Box<dyn BufRead + 'a>> {
+    let mut reader = BufReader::new(reader);
+    if !reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        return Ok(Box::new(reader));
     }
-    String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    Ok(Box::new(BufReader::new(CappedReader {
+        inner: GzDecoder::new(reader),
+        remaining: max_bytes,
+        max_bytes,
+    })))
 }
-pub fn load_json_from_file(
-    log_contents: &str,
+
+/// Stream the normalized events of one input file to `process_events`, in chunks of at most
+/// `EVENT_CHUNK_SIZE` events, without reading the file into memory as a whole. `-` reads
+/// standard input (e.g. `aws s3 cp s3://bucket/key - | suzaku ... -f -`); gzip-compressed input
+/// is detected by its magic bytes, so piping through `zcat` is optional. Security Lake Parquet
+/// files are read row by row, archives member by member (calling `on_member` with each member's
+/// path), and CSV exports (e.g. CloudTrail Event history) row by row.
+///
+/// On an error, the events read before it have already been processed.
+pub fn stream_events_from_file(
+    path: &Path,
     log: &LogSource,
-) -> Result<Vec<Value>, Box<dyn Error>> {
-    let mut events = Vec::new();
-    match log {
-        LogSource::Aws => {
-            let log_contents_trimmed = log_contents
-                .strip_prefix('\u{FEFF}')
-                .unwrap_or(log_contents);
-            match serde_json::from_str::<Value>(log_contents_trimmed) {
-                // Array, `{ "Records": [...] }` batch, or a single event object.
-                Ok(json_value) => events.extend(aws_records(json_value)),
-                Err(_) => {
-                    // Fall back to JSONL (each line may itself be a `Records` batch).
-                    log_contents.lines().for_each(|line| {
-                        if let Ok(json_value) = serde_json::from_str::<Value>(line) {
-                            events.extend(aws_records(json_value));
-                        }
-                    });
-                }
-            }
+    process_events: &mut dyn FnMut(&[Value]),
+    on_member: &dyn Fn(&str),
+) -> Result<(), Box<dyn Error>> {
+    stream_chunks_from_file(
+        path,
+        log,
+        &mut |events| process_events(&events),
+        on_member,
+        false,
+    )
+    .0
+}
+
+/// `stream_events_from_file`, handing over ownership of each chunk. With `hash`, also returns
+/// the digest of the file (`None` if it could not be read to the end). Plain and gzip files and
+/// stdin are hashed from the bytes the scan reads; Parquet files and archives are not read front
+/// to back, so they are hashed by reading them a second time, before the scan.
+fn stream_chunks_from_file(
+    path: &Path,
+    log: &LogSource,
+    process_events: &mut dyn FnMut(Vec<Value>),
+    on_member: &dyn Fn(&str),
+    hash: bool,
+) -> (Result<(), Box<dyn Error>>, Option<FileDigest>) {
+    let path_str = path.to_string_lossy();
+    let mut chunker = EventChunker {
+        log,
+        records: Vec::with_capacity(EVENT_CHUNK_SIZE),
+        process_events,
+    };
+    let mut emit = |record| chunker.push(record);
+    let is_parquet =
+        matches!(log, LogSource::Aws | LogSource::All) && path_str.ends_with(".parquet");
+    let mut digest = None;
+    let result: Result<(), Box<dyn Error>> = if is_stdin(path) {
+        let result;
+        (result, digest) = read_hashing(io::stdin().lock(), hash, |stdin| {
+            read_records_detecting_gzip(stdin, "-", log, &mut emit)
+        });
+        result.map_err(Into::into)
+    } else if is_parquet {
+        if hash {
+            digest = file_digest(path).ok();
         }
-        LogSource::Azure => {
-            let log_contents_trimmed = log_contents
-                .strip_prefix('\u{FEFF}')
-                .unwrap_or(log_contents);
-            let json_value: Result<Value, _> = serde_json::from_str(log_contents_trimmed);
-            match json_value {
-                // Array, `{ records|value: [...] }` batch envelope, or a single record.
-                Ok(json_value) => events.extend(azure_records(json_value)),
-                Err(_) => {
-                    // Fall back to JSONL (each line may itself be a `records` batch).
-                    log_contents.lines().for_each(|line| {
-                        if let Ok(json_value) = serde_json::from_str::<Value>(line) {
-                            events.extend(azure_records(json_value));
-                        }
-                    });
-                }
-            }
+        security_lake::read_parquet_file(path, &mut emit)
+    } else if archive::is_archive(&path_str) {
+        if hash {
+            digest = file_digest(path).ok();
         }
+        archive::visit_archive(
+            path,
+            MAX_DECOMPRESSED_BYTES,
+            &|name| is_log_member(name, log),
+            |member, reader| {
+                on_member(member);
+                read_records(reader, member, log, &mut emit)
+            },
+        )
+        .map_err(Into::into)
+    } else {
+        File::open(path)
+            .and_then(|file| {
+                let result;
+                (result, digest) = read_hashing(file, hash, |file| {
+                    if path_str.ends_with(".gz") {
+                        gzip_aware_reader(file, MAX_DECOMPRESSED_BYTES)
+                    } else {
+                        Ok(Box::new(BufReader::new(file)) as Box<dyn BufRead>)
+                    }
+                    .and_then(|mut reader| read_records(&mut reader, &path_str, log, &mut emit))
+                });
+                result
+            })
+            .map_err(Into::into)
+    };
+    chunker.flush();
+    (result, digest)
+}
 
-        _ => {}
+/// Call `read` with `reader`, and if `hash`, return the digest of all of its bytes too.
+fn read_hashing<R: Read, T>(
+    mut reader: R,
+    hash: bool,
+    read: impl FnOnce(&mut dyn Read) -> T,
+) -> (T, Option<FileDigest>) {
+    if !hash {
+        return (read(&mut reader), None);
     }
-    Ok(events)
+    let mut reader = HashingReader::new(reader);
+    let result = read(&mut reader);
+    (result, reader.finish())
 }
 
-pub fn get_content(f: &PathBuf) -> String {
-    let path = f.display().to_string();
-    let result = if path.ends_with(".json") || path.ends_with(".jsonl") || path.ends_with(".csv") {
-        fs::read_to_string(f)
-    } else if path.ends_with(".gz") {
-        read_gz_file(f)
-    } else {
-        return "".to_string();
+/// `read_records` on input that may or may not be gzip-compressed, whatever its name says.
+fn read_records_detecting_gzip(
+    reader: impl Read,
+    name: &str,
+    log: &LogSource,
+    emit: &mut dyn FnMut(Value),
+) -> io::Result<()> {
+    let mut reader = gzip_aware_reader(reader, MAX_DECOMPRESSED_BYTES)?;
+    read_records(&mut reader, name, log, emit)
+}
+
+/// `stream_chunks_from_file` for an object in remote storage, streamed as it downloads. Parquet
+/// files and archives need random access (or, for tar, are read with the same code as on disk),
+/// so those are downloaded to a temporary file first, and hashed as they download.
+fn stream_chunks_from_object(
+    store: &dyn ObjectStore,
+    object: &RemoteObject,
+    log: &LogSource,
+    process_events: &mut dyn FnMut(Vec<Value>),
+    on_member: &dyn Fn(&str),
+    hash: bool,
+) -> (Result<(), Box<dyn Error>>, Option<FileDigest>) {
+    let url = store.url(&object.key);
+    let reader = match store.get(&object.key) {
+        Ok(reader) => reader,
+        Err(e) => return (Err(e.into()), None),
+    };
+    if url.ends_with(".parquet") || archive::is_archive(&url) {
+        let name = url.rsplit('/').next().unwrap_or_default();
+        let mut temp = match tempfile::Builder::new()
+            .suffix(&format!("-{name}"))
+            .tempfile()
+        {
+            Ok(temp) => temp,
+            Err(e) => return (Err(e.into()), None),
+        };
+        let (downloaded, digest) = read_hashing(reader, hash, |reader| io::copy(reader, &mut temp));
+        if let Err(e) = downloaded {
+            return (Err(e.into()), digest);
+        }
+        let (result, _) =
+            stream_chunks_from_file(temp.path(), log, process_events, on_member, false);
+        return (result, digest);
+    }
+    let mut chunker = EventChunker {
+        log,
+        records: Vec::with_capacity(EVENT_CHUNK_SIZE),
+        process_events,
     };
-    // Warn instead of silently returning empty content on a read failure.
-    result.unwrap_or_else(|e| {
-        eprintln!("[WARNING] Skipping {path}: {e}");
-        String::new()
-    })
+    let (result, digest) = read_hashing(reader, hash, |reader| {
+        read_records_detecting_gzip(reader, &url, log, &mut |record| chunker.push(record))
+    });
+    chunker.flush();
+    (result.map_err(Into::into), digest)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// The records `read_records` streams from `contents`, read as a file named `path`, before
+    /// normalization.
+    fn read_records_from_str(
+        contents: &str,
+        path: &str,
+        log: &LogSource,
+    ) -> io::Result<Vec<Value>> {
+        let mut records = Vec::new();
+        read_records(&mut contents.as_bytes(), path, log, &mut |v| {
+            records.push(v)
+        })?;
+        Ok(records)
+    }
+
+    /// The events the scan processes from `contents`: its records normalized with
+    /// `normalize_events`, as `EventChunker` does.
+    fn read_events_from_str(contents: &str, log: &LogSource) -> Vec<Value> {
+        normalize_events(read_records_from_str(contents, "", log).unwrap(), log)
+    }
+
+    /// Every normalized event `stream_events_from_file` reads from `path`.
+    fn stream_events(path: &Path, log: &LogSource) -> Result<Vec<Value>, Box<dyn Error>> {
+        let mut events = Vec::new();
+        stream_events_from_file(path, log, &mut |e| events.extend_from_slice(e), &|_| {})?;
+        Ok(events)
+    }
+
     #[test]
-    fn test_load_event_from_file() {
+    fn test_read_records_cloudtrail_batch_file() {
         let test_file = "test_files/json/DeleteTrail.json";
         let log_contents = fs::read_to_string(test_file).unwrap();
-        let result = load_json_from_file(&log_contents, &LogSource::Aws);
+        let result = read_records_from_str(&log_contents, "", &LogSource::Aws);
         assert!(result.is_ok());
         let event = result.unwrap();
         assert_eq!(event.len(), 1);
     }
 
     #[test]
-    fn test_load_event_from_file_record() {
+    fn test_read_records_cloudtrail_records_file() {
         let test_file = "test_files/json/test.json";
         let log_contents = fs::read_to_string(test_file).unwrap();
-        let result = load_json_from_file(&log_contents, &LogSource::Aws);
+        let result = read_records_from_str(&log_contents, "", &LogSource::Aws);
         assert!(result.is_ok());
         let event = result.unwrap();
         assert_eq!(event.len(), 29);
@@ -745,7 +1867,7 @@ mod tests {
     fn test_load_azure_value_format() {
         let test_file = "test_files/json/azure_value_format.json";
         let log_contents = fs::read_to_string(test_file).unwrap();
-        let result = load_json_from_file(&log_contents, &LogSource::Azure);
+        let result = read_records_from_str(&log_contents, "", &LogSource::Azure);
         assert!(result.is_ok());
         let events = result.unwrap();
         assert_eq!(events.len(), 1);
@@ -761,7 +1883,7 @@ mod tests {
     fn test_load_azure_graph_api_format() {
         let test_file = "test_files/json/azure_graph_api_format.json";
         let log_contents = fs::read_to_string(test_file).unwrap();
-        let result = load_json_from_file(&log_contents, &LogSource::Azure);
+        let result = read_records_from_str(&log_contents, "", &LogSource::Azure);
         assert!(result.is_ok());
         let events = result.unwrap();
         assert_eq!(events.len(), 3);
@@ -842,10 +1964,10 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_csv_events_unified_audit_log() {
+    fn test_read_records_unified_audit_log_csv() {
         let csv = "\"RecordType\",\"Operations\",\"AuditData\"\r\n\
             \"AzureActiveDirectory\",\"UserLoggedIn\",\"{\"\"Operation\"\":\"\"UserLoggedIn\"\",\"\"Workload\"\":\"\"AzureActiveDirectory\"\"}\"\r\n";
-        let rows = parse_csv_events(csv);
+        let rows = read_records_from_str(csv, "export.csv", &LogSource::All).unwrap();
         assert_eq!(rows.len(), 1);
         // Row carries AuditData as a string; normalization unwraps it to the record.
         let ev = normalize_azure_event(rows.into_iter().next().unwrap());
@@ -855,6 +1977,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cloudtrail_event_history_csv_is_unwrapped() {
+        let test_file = PathBuf::from("test_files/csv/cloudtrail_event_history.csv");
+        let events = stream_events(&test_file, &LogSource::Aws).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["eventName"], "StopLogging");
+        assert_eq!(
+            events[0]["userIdentity"]["arn"],
+            "arn:aws:iam::123456789012:user/alice"
+        );
+        assert_eq!(events[0]["requestParameters"]["name"], "management-events");
+        assert_eq!(events[1]["eventName"], "ConsoleLogin");
+        assert!(events[1].get("CloudTrailEvent").is_none());
+    }
+
+    #[test]
+    fn test_lookup_events_response_is_unwrapped() {
+        let contents = r#"{"Events":[{"EventId":"e1","EventName":"DeleteTrail","CloudTrailEvent":"{\"eventName\":\"DeleteTrail\",\"eventSource\":\"cloudtrail.amazonaws.com\",\"eventTime\":\"2024-01-15T03:04:05Z\"}"}],"NextToken":"abc"}"#;
+        let events = read_events_from_str(contents, &LogSource::Aws);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["eventSource"], "cloudtrail.amazonaws.com");
+        assert_eq!(events[0]["eventTime"], "2024-01-15T03:04:05Z");
+        assert!(events[0].get("EventId").is_none());
+        // A row whose `CloudTrailEvent` is not valid JSON is kept as-is.
+        let row = serde_json::json!({"EventName": "DeleteTrail", "CloudTrailEvent": "not json"});
+        assert_eq!(normalize_aws_event(row.clone()), row);
+    }
+
     #[test]
     fn test_normalize_synthesizes_deterministic_details_summary() {
         // The `_Details` field summarizes the change (Exchange cmdlet Parameters)
@@ -882,7 +2032,7 @@ mod tests {
         // Azure Monitor diagnostic-settings / Event Hub blobs wrap events as
         // `{ "records": [...] }`; each record must become its own event.
         let contents = r#"{"records":[{"category":"SignInLogs","properties":{"a":1}},{"category":"SignInLogs","properties":{"a":2}}]}"#;
-        let events = log_contents_to_events(contents, &LogSource::Azure);
+        let events = read_records_from_str(contents, "", &LogSource::Azure).unwrap();
         assert_eq!(events.len(), 2);
         assert_eq!(
             events[0].get("category").unwrap().as_str().unwrap(),
@@ -898,7 +2048,7 @@ mod tests {
             "\n",
             r#"{"records":[{"category":"AuditLogs","properties":{"a":2}},{"category":"AuditLogs","properties":{"a":3}}]}"#,
         );
-        let events = log_contents_to_events(contents, &LogSource::Azure);
+        let events = read_records_from_str(contents, "", &LogSource::Azure).unwrap();
         assert_eq!(events.len(), 3);
     }
 
@@ -922,7 +2072,7 @@ mod tests {
     fn test_azure_single_object_json_is_one_event() {
         // A bare (or pretty-printed) single JSON object must parse to one event.
         let contents = "{\n  \"Operation\": \"Set-Mailbox\",\n  \"Workload\": \"Exchange\"\n}";
-        let events = log_contents_to_events(contents, &LogSource::Azure);
+        let events = read_records_from_str(contents, "", &LogSource::Azure).unwrap();
         assert_eq!(events.len(), 1);
         assert_eq!(
             events[0].get("Operation").unwrap().as_str().unwrap(),
@@ -957,7 +2107,7 @@ mod tests {
             "\n",
             r#"{"eventName":"PutObject","eventSource":"s3.amazonaws.com"}"#,
         );
-        let events = log_contents_to_events(contents, &LogSource::Aws);
+        let events = read_records_from_str(contents, "", &LogSource::Aws).unwrap();
         assert_eq!(events.len(), 3);
         assert_eq!(
             events[0].get("eventName").unwrap().as_str().unwrap(),
@@ -973,47 +2123,898 @@ mod tests {
             "\n",
             r#"{"Records":[{"eventName":"B"},{"eventName":"C"}]}"#,
         );
-        assert_eq!(log_contents_to_events(contents, &LogSource::Aws).len(), 3);
+        assert_eq!(
+            read_records_from_str(contents, "", &LogSource::Aws)
+                .unwrap()
+                .len(),
+            3
+        );
     }
 
     #[test]
     fn test_aws_batch_and_array_whole_file_still_parse() {
         // Regression: the standard whole-file shapes must keep working.
         let batch = r#"{"Records":[{"eventName":"A"},{"eventName":"B"}]}"#;
-        assert_eq!(log_contents_to_events(batch, &LogSource::Aws).len(), 2);
+        assert_eq!(
+            read_records_from_str(batch, "", &LogSource::Aws)
+                .unwrap()
+                .len(),
+            2
+        );
         let array = r#"[{"eventName":"A"},{"eventName":"B"},{"eventName":"C"}]"#;
-        assert_eq!(log_contents_to_events(array, &LogSource::Aws).len(), 3);
+        assert_eq!(
+            read_records_from_str(array, "", &LogSource::Aws)
+                .unwrap()
+                .len(),
+            3
+        );
     }
 
     #[test]
-    fn test_load_json_from_file_aws_handles_jsonl() {
+    fn test_read_records_aws_handles_jsonl() {
         // The path used by aws-ct-metrics/search/summary must also read JSONL.
         let contents = concat!(
             r#"{"eventName":"A"}"#,
             "\n",
             r#"{"Records":[{"eventName":"B"},{"eventName":"C"}]}"#,
         );
-        let events = load_json_from_file(contents, &LogSource::Aws).unwrap();
+        let events = read_records_from_str(contents, "", &LogSource::Aws).unwrap();
         assert_eq!(events.len(), 3);
     }
 
-    fn write_gz(path: &PathBuf, decompressed: &[u8]) {
-        use flate2::Compression;
-        use flate2::write::GzEncoder;
-        use std::io::Write as _;
-        let mut enc = GzEncoder::new(File::create(path).unwrap(), Compression::default());
-        enc.write_all(decompressed).unwrap();
-        enc.finish().unwrap();
+    #[test]
+    fn test_load_gcp_audit_log_export() {
+        let test_file = "test_files/json/gcp_audit_log.json";
+        let log_contents = fs::read_to_string(test_file).unwrap();
+        let events = read_records_from_str(&log_contents, "", &LogSource::Gcp).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0]
+                .pointer("/protoPayload/methodName")
+                .unwrap()
+                .as_str()
+                .unwrap(),
+            "SetIamPolicy"
+        );
     }
 
     #[test]
-    fn read_gz_file_capped_rejects_oversized_decompression() {
-        // 200 decompressed bytes with a 16-byte cap must error (not return partial data),
-        // so the caller skips the file instead of the process OOM-ing on a bomb.
-        let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("bomb.json.gz");
-        write_gz(&path, &[b'A'; 200]);
-        assert!(read_gz_file_capped(&path, 16).is_err());
+    fn test_gcp_records_helper_shapes() {
+        // bare array (`gcloud logging read --format=json`)
+        assert_eq!(gcp_records(serde_json::json!([{"x":1},{"x":2}])).len(), 2);
+        // Logging API `{ "entries": [...] }` response
+        assert_eq!(
+            gcp_records(serde_json::json!({"entries":[{"x":1}],"nextPageToken":"t"})).len(),
+            1
+        );
+        // single entry (one JSONL line)
+        assert_eq!(
+            gcp_records(serde_json::json!({"insertId":"a","logName":"l"})).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_gcp_jsonl_sink_content_is_parsed() {
+        // GCS log sinks write one LogEntry per line.
+        let contents = concat!(
+            r#"{"insertId":"a","protoPayload":{"methodName":"storage.buckets.list"}}"#,
+            "\n",
+            r#"{"insertId":"b","protoPayload":{"methodName":"storage.buckets.delete"}}"#,
+        );
+        assert_eq!(
+            read_records_from_str(contents, "", &LogSource::Gcp)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_normalize_gcp_event_adds_sigma_audit_aliases() {
+        let entry = serde_json::json!({
+            "protoPayload": {
+                "@type": "type.googleapis.com/google.cloud.audit.AuditLog",
+                "methodName": "storage.buckets.delete",
+                "serviceName": "storage.googleapis.com",
+                "authenticationInfo": {"principalEmail": "attacker@example.com"},
+                "requestMetadata": {"callerIp": "203.0.113.5"}
+            }
+        });
+        let ev = normalize_gcp_event(entry);
+        assert_eq!(
+            ev.pointer("/gcp/audit/method_name").unwrap(),
+            "storage.buckets.delete"
+        );
+        assert_eq!(
+            ev.pointer("/gcp/audit/principal_email").unwrap(),
+            "attacker@example.com"
+        );
+        // The original envelope is kept for the output profile.
+        assert_eq!(
+            ev.pointer("/protoPayload/methodName").unwrap(),
+            "storage.buckets.delete"
+        );
+    }
+
+    #[test]
+    fn test_normalize_gcp_event_leaves_non_audit_entry_untouched() {
+        let entry = serde_json::json!({"logName": "projects/p/logs/stdout", "textPayload": "x"});
+        assert_eq!(normalize_gcp_event(entry.clone()), entry);
+    }
+
+    #[test]
+    fn test_load_okta_system_log_export() {
+        let test_file = "test_files/json/okta_system_log.json";
+        let log_contents = fs::read_to_string(test_file).unwrap();
+        let events = read_records_from_str(&log_contents, "", &LogSource::Okta).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1].get("eventType").unwrap().as_str().unwrap(),
+            "user.mfa.factor.deactivate"
+        );
+    }
+
+    #[test]
+    fn test_okta_jsonl_content_is_parsed() {
+        let contents = concat!(
+            r#"{"published":"2024-01-15T03:04:05.123Z","eventType":"user.session.start"}"#,
+            "\n",
+            r#"{"published":"2024-01-15T03:10:42.456Z","eventType":"user.session.end"}"#,
+        );
+        assert_eq!(
+            read_records_from_str(contents, "", &LogSource::Okta)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_normalize_okta_event_adds_lowercase_aliases() {
+        let event = serde_json::json!({
+            "eventType": "user.session.start",
+            "displayMessage": "User login to Okta",
+            "securityContext": {"isProxy": true}
+        });
+        let ev = normalize_okta_event(event);
+        assert_eq!(ev.get("eventtype").unwrap(), "user.session.start");
+        assert_eq!(ev.get("displaymessage").unwrap(), "User login to Okta");
+        assert_eq!(ev.pointer("/securitycontext/isproxy").unwrap(), true);
+        // The original fields are kept for the output profile.
+        assert_eq!(ev.get("eventType").unwrap(), "user.session.start");
+    }
+
+    #[test]
+    fn test_load_gws_reports_api_response() {
+        let test_file = "test_files/json/gws_login_activities.json";
+        let log_contents = fs::read_to_string(test_file).unwrap();
+        let events = read_records_from_str(&log_contents, "", &LogSource::Gws).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            gws_records(serde_json::json!({"kind":"admin#reports#activities"})).len(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_normalize_gws_activity_splits_events_and_folds_parameters() {
+        let activity = serde_json::json!({
+            "id": {"time": "2024-03-01T09:15:00.000Z", "applicationName": "login"},
+            "actor": {"email": "alice@example.com"},
+            "ipAddress": "198.51.100.23",
+            "events": [
+                {
+                    "type": "login",
+                    "name": "login_success",
+                    "parameters": [
+                        {"name": "login_type", "value": "google_password"},
+                        {"name": "is_suspicious", "boolValue": true},
+                        {"name": "login_challenge_method", "multiValue": ["password", "totp"]}
+                    ]
+                },
+                {"type": "login", "name": "login_verification"}
+            ]
+        });
+        let records = normalize_gws_activity(activity);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].get("eventName").unwrap(), "login_success");
+        assert_eq!(
+            records[0].pointer("/parameters/login_type").unwrap(),
+            "google_password"
+        );
+        assert_eq!(
+            records[0].pointer("/parameters/is_suspicious").unwrap(),
+            true
+        );
+        assert_eq!(
+            records[0]
+                .pointer("/parameters/login_challenge_method/1")
+                .unwrap(),
+            "totp"
+        );
+        // Activity-level fields are copied to every record.
+        assert_eq!(records[1].get("eventName").unwrap(), "login_verification");
+        assert_eq!(
+            records[1].pointer("/actor/email").unwrap(),
+            "alice@example.com"
+        );
+        assert!(records[1].get("events").is_none());
+    }
+
+    #[test]
+    fn test_fold_gws_parameters_nested_messages() {
+        let params = serde_json::json!([
+            {"name": "scope_data", "multiMessageValue": [
+                {"parameter": [{"name": "scope_name", "value": "https://mail.google.com/"}]}
+            ]},
+            {"name": "app", "messageValue": {"parameter": [{"name": "id", "intValue": "42"}]}}
+        ]);
+        let folded = fold_gws_parameters(params.as_array().unwrap());
+        assert_eq!(
+            folded["scope_data"][0]["scope_name"],
+            "https://mail.google.com/"
+        );
+        assert_eq!(folded["app"]["id"], "42");
+    }
+
+    #[test]
+    fn test_load_k8s_audit_jsonl() {
+        let test_file = "test_files/json/k8s_audit.jsonl";
+        let log_contents = fs::read_to_string(test_file).unwrap();
+        let events = read_records_from_str(&log_contents, "", &LogSource::Kubernetes).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].pointer("/objectRef/subresource").unwrap(), "exec");
+    }
+
+    #[test]
+    fn test_k8s_eks_cloudwatch_wrappers_are_unwrapped() {
+        let event =
+            r#"{"kind":"Event","apiVersion":"audit.k8s.io/v1","auditID":"a1","verb":"get"}"#;
+        // CloudWatch Logs export: `<timestamp> <event json>` per line.
+        let exported =
+            format!("2024-01-15T03:04:05.123Z {event}\n2024-01-15T03:04:06.000Z {event}");
+        assert_eq!(
+            read_records_from_str(&exported, "", &LogSource::Kubernetes)
+                .unwrap()
+                .len(),
+            2
+        );
+        // Subscription filter / Firehose envelope.
+        let envelope = serde_json::json!({
+            "messageType": "DATA_MESSAGE",
+            "logGroup": "/aws/eks/prod/cluster",
+            "logStream": "kube-apiserver-audit-0123",
+            "logEvents": [{"id": "1", "timestamp": 1705287845123_i64, "message": event}]
+        });
+        let events =
+            read_records_from_str(&envelope.to_string(), "", &LogSource::Kubernetes).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].get("auditID").unwrap(), "a1");
+    }
+
+    #[test]
+    fn test_aws_cloudwatch_logs_export_is_parsed() {
+        let event = r#"{"eventName":"StopLogging","eventSource":"cloudtrail.amazonaws.com","eventTime":"2024-01-15T03:04:05Z"}"#;
+        // `CreateExportTask` output: `<timestamp> <event json>` per line.
+        let exported =
+            format!("2024-01-15T03:04:05.123Z {event}\n2024-01-15T03:04:06.000Z {event}\n");
+        assert_eq!(
+            read_records_from_str(&exported, "", &LogSource::Aws)
+                .unwrap()
+                .len(),
+            2
+        );
+        let events = read_records_from_str(&exported, "", &LogSource::Aws).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["eventName"], "StopLogging");
+
+        // Subscription filter batches, concatenated without a separator by Firehose.
+        let batch = serde_json::json!({
+            "messageType": "DATA_MESSAGE",
+            "logGroup": "aws-cloudtrail-logs-123456789012",
+            "logEvents": [
+                {"id": "1", "timestamp": 1705287845123_i64, "message": event},
+                {"id": "2", "timestamp": 1705287846000_i64, "message": event}
+            ]
+        });
+        let control = serde_json::json!({
+            "messageType": "CONTROL_MESSAGE",
+            "logEvents": [{"id": "", "timestamp": 1705287845123_i64, "message": "CWL CONTROL MESSAGE: Checking health of destination Firehose."}]
+        });
+        let firehose = format!("{batch}{control}{batch}");
+        assert_eq!(
+            read_records_from_str(&firehose, "", &LogSource::Aws)
+                .unwrap()
+                .len(),
+            4
+        );
+        assert_eq!(
+            read_records_from_str(&firehose, "", &LogSource::Aws)
+                .unwrap()
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_normalize_k8s_event_exposes_first_source_ip() {
+        let event = serde_json::json!({"auditID": "a1", "sourceIPs": ["203.0.113.9", "10.0.0.1"]});
+        assert_eq!(
+            normalize_k8s_event(event).get("sourceIP").unwrap(),
+            "203.0.113.9"
+        );
+    }
+
+    #[test]
+    fn test_load_vpc_flow_log_file() {
+        let test_file = PathBuf::from("test_files/vpcflow/vpc_flow_log.log");
+        let log_contents = fs::read_to_string(&test_file).unwrap();
+        let events = read_records_from_str(&log_contents, "", &LogSource::VpcFlow).unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[1]["dstaddr"], "198.51.100.7");
+        assert_eq!(events[1]["bytes"], 10485760);
+        // JSON content is not mistaken for a flow log.
+        assert!(
+            read_records_from_str(r#"{"Records":[]}"#, "", &LogSource::VpcFlow)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn text_log_files_are_only_collected_for_vpc_flow_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("flows.log"), b"").unwrap();
+        fs::write(dir.path().join("trail.json"), b"[]").unwrap();
+        let root = dir.path().to_path_buf();
+        let opt = FileDateOption::default();
+        assert_eq!(
+            count_files_recursive(&root, &opt, &LogSource::VpcFlow)
+                .unwrap()
+                .0,
+            2
+        );
+        assert_eq!(
+            count_files_recursive(&root, &opt, &LogSource::Aws)
+                .unwrap()
+                .0,
+            1
+        );
+    }
+
+    #[test]
+    fn test_load_s3_access_log_object_without_extension() {
+        let test_file = PathBuf::from("test_files/s3access/2024-01-15-03-00-00-0123456789ABCDEF");
+        let log_contents = fs::read_to_string(&test_file).unwrap();
+        let events = read_records_from_str(&log_contents, "", &LogSource::S3Access).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2]["operation"], "REST.GET.OBJECT");
+        assert_eq!(events[2]["bytesSent"], 52428800);
+        assert!(is_log_file(&test_file, &LogSource::S3Access));
+        assert!(!is_log_file(&test_file, &LogSource::Aws));
+    }
+
+    #[test]
+    fn test_load_security_lake_parquet_and_ocsf_json() {
+        let test_file = PathBuf::from("test_files/securitylake/cloudtrail_mgmt.zstd.parquet");
+        assert!(is_log_file(&test_file, &LogSource::Aws));
+        assert!(!is_log_file(&test_file, &LogSource::Azure));
+        let events = stream_events(&test_file, &LogSource::Aws).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1]["eventName"], "ConsoleLogin");
+        assert_eq!(events[1]["sourceIPAddress"], "198.51.100.20");
+        assert_eq!(events[1]["userIdentity"]["type"], "IAMUser");
+
+        // The same OCSF records exported as JSON Lines (e.g. from an Athena query).
+        let contents = r#"{"class_uid":6003,"time":1705287845000,"metadata":{"product":{"name":"CloudTrail"}},"api":{"operation":"StopLogging","service":{"name":"cloudtrail.amazonaws.com"}}}
+{"eventName":"DeleteTrail","eventSource":"cloudtrail.amazonaws.com"}"#;
+        let events = read_events_from_str(contents, &LogSource::Aws);
+        assert_eq!(events[0]["eventName"], "StopLogging");
+        assert_eq!(events[0]["eventTime"], "2024-01-15T03:04:05Z");
+        assert_eq!(events[1]["eventName"], "DeleteTrail");
+    }
+
+    #[test]
+    fn gzip_aware_reader_accepts_plain_and_gzip_input() {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use std::io::Write as _;
+        let jsonl = b"{\"eventName\":\"StopLogging\"}\n{\"eventName\":\"DeleteTrail\"}\n";
+        let mut plain = String::new();
+        gzip_aware_reader(&jsonl[..], 8)
+            .unwrap()
+            .read_to_string(&mut plain)
+            .unwrap();
+        assert_eq!(
+            read_records_from_str(&plain, "", &LogSource::Aws)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(jsonl).unwrap();
+        let gz = enc.finish().unwrap();
+        let mut decompressed = String::new();
+        gzip_aware_reader(&gz[..], 1024)
+            .unwrap()
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, plain);
+
+        let mut decompressed = String::new();
+        let result = gzip_aware_reader(&gz[..], 8)
+            .unwrap()
+            .read_to_string(&mut decompressed);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_stream_events_from_file_emits_bounded_chunks() {
+        let records: Vec<Value> = (0..EVENT_CHUNK_SIZE * 2 + 5)
+            .map(|i| serde_json::json!({"eventName": "GetObject", "eventID": i.to_string()}))
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trail.json.gz");
+        write_gz(
+            &path,
+            serde_json::json!({ "Records": records })
+                .to_string()
+                .as_bytes(),
+        );
+
+        let mut chunk_sizes = Vec::new();
+        stream_events_from_file(
+            &path,
+            &LogSource::Aws,
+            &mut |e| chunk_sizes.push(e.len()),
+            &|_| {},
+        )
+        .unwrap();
+        assert_eq!(chunk_sizes, vec![EVENT_CHUNK_SIZE, EVENT_CHUNK_SIZE, 5]);
+    }
+
+    #[test]
+    fn test_process_events_from_dir_scans_archive_members() {
+        use std::io::Write as _;
+        let dir = tempfile::tempdir().unwrap();
+        let mut zip = zip::ZipWriter::new(File::create(dir.path().join("export.zip")).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("2024/01/15/trail.json", options).unwrap();
+        zip.write_all(&fs::read("test_files/json/DeleteTrail.json").unwrap())
+            .unwrap();
+        zip.start_file("notes.docx", options).unwrap();
+        zip.write_all(b"not a log").unwrap();
+        zip.finish().unwrap();
+
+        let mut events = Vec::new();
+        process_events_from_dir(
+            |e: &[Value]| events.extend_from_slice(e),
+            &dir.path().to_path_buf(),
+            false,
+            true,
+            &LogSource::Aws,
+            &FileDateOption::default(),
+            None,
+            false,
+        )
+        .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["eventName"], "DeleteTrail");
+    }
+
+    #[test]
+    fn test_process_events_from_dir_keeps_file_order_when_reading_in_parallel() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut expected = Vec::new();
+        for i in 0..40 {
+            // Uneven sizes, so later files are often parsed before earlier ones.
+            let count = if i % 7 == 0 {
+                EVENT_CHUNK_SIZE * 3 + 1
+            } else {
+                3
+            };
+            let records: Vec<Value> = (0..count)
+                .map(|j| serde_json::json!({"eventID": format!("{i:02}-{j}")}))
+                .collect();
+            expected.extend(records.iter().map(|r| r["eventID"].clone()));
+            write_gz(
+                &dir.path().join(format!("{i:02}.json.gz")),
+                serde_json::json!({ "Records": records })
+                    .to_string()
+                    .as_bytes(),
+            );
+        }
+
+        let mut events = Vec::new();
+        process_events_from_dir(
+            |e: &[Value]| events.extend(e.iter().map(|r| r["eventID"].clone())),
+            &dir.path().to_path_buf(),
+            false,
+            true,
+            &LogSource::Aws,
+            &FileDateOption::default(),
+            None,
+            false,
+        )
+        .unwrap();
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn test_process_events_from_dir_does_not_rescan_failed_files_until_they_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let record = |id: &str| serde_json::json!({"Records": [{"eventID": id}]}).to_string();
+        write_gz(&dir.path().join("a.json.gz"), record("a").as_bytes());
+        fs::write(dir.path().join("b.json.gz"), b"not gzip").unwrap();
+
+        let mut state = ScanState::default();
+        let scan = |state: &mut ScanState| {
+            let mut events = Vec::new();
+            process_events_from_dir(
+                |e: &[Value]| events.extend(e.iter().map(|r| r["eventID"].clone())),
+                &dir.path().to_path_buf(),
+                false,
+                true,
+                &LogSource::Aws,
+                &FileDateOption::default(),
+                Some(state),
+                false,
+            )
+            .unwrap();
+            events
+        };
+        assert_eq!(scan(&mut state), vec!["a"]);
+        assert!(scan(&mut state).is_empty());
+
+        write_gz(&dir.path().join("b.json.gz"), record("b").as_bytes());
+        assert_eq!(scan(&mut state), vec!["b"]);
+    }
+
+    #[test]
+    fn test_process_events_from_dir_records_the_files_it_reads_for_the_manifest() {
+        use crate::core::manifest::tests::recorded_input;
+        use crate::core::util::sha256_file;
+
+        let dir = tempfile::tempdir().unwrap();
+        let record = serde_json::json!({"Records": [{"eventID": "a"}]}).to_string();
+        let plain = dir.path().join("a.json");
+        fs::write(&plain, &record).unwrap();
+        let gz = dir.path().join("b.json.gz");
+        write_gz(&gz, record.as_bytes());
+        let zip = dir.path().join("c.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&zip).unwrap());
+        writer
+            .start_file("c.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        std::io::Write::write_all(&mut writer, record.as_bytes()).unwrap();
+        writer.finish().unwrap();
+
+        manifest::start_recording();
+        process_events_from_dir(
+            |_: &[Value]| {},
+            &dir.path().to_path_buf(),
+            false,
+            true,
+            &LogSource::Aws,
+            &FileDateOption::default(),
+            None,
+            true,
+        )
+        .unwrap();
+        for path in [plain, gz, zip] {
+            let entry = recorded_input(&path.to_string_lossy()).unwrap();
+            assert_eq!(entry.size, Some(fs::metadata(&path).unwrap().len()));
+            assert_eq!(entry.sha256, Some(sha256_file(&path).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_correlation_events_are_numbered_in_the_order_they_are_matched() {
+        let json = serde_json::json!({"eventName": "StopLogging"});
+        let event = event_from_json(&json.to_string()).unwrap();
+        let seq = |event: &Event| {
+            let seq = event.get(CORRELATION_SEQ_FIELD).unwrap().value_to_string();
+            seq.parse::<u64>().unwrap()
+        };
+        let first_new = next_correlation_seq();
+        let a = with_correlation_seq(&json, &event);
+        let b = with_correlation_seq(&json, &event);
+        assert!(seq(&a) >= first_new);
+        assert!(seq(&b) > seq(&a));
+        assert_eq!(a.get("eventName").unwrap().value_to_string(), "StopLogging");
+    }
+
+    #[test]
+    fn test_process_events_from_dir_does_not_record_files_without_a_manifest() {
+        use crate::core::manifest::tests::recorded_input;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.json");
+        fs::write(&path, r#"{"Records": [{"eventID": "a"}]}"#).unwrap();
+        manifest::start_recording();
+        process_events_from_dir(
+            |_: &[Value]| {},
+            &dir.path().to_path_buf(),
+            false,
+            true,
+            &LogSource::Aws,
+            &FileDateOption::default(),
+            None,
+            false,
+        )
+        .unwrap();
+        assert!(recorded_input(&path.to_string_lossy()).is_none());
+    }
+
+    #[test]
+    fn test_scan_new_files_scans_each_file_once_its_size_is_stable() {
+        use crate::core::timeline_writer::{OutputConfig, Writers};
+
+        let dir = tempfile::tempdir().unwrap();
+        let d = dir.path().to_path_buf();
+        let profile = Vec::new();
+        let mut geo = None;
+        let config = OutputConfig::new(true, false, false);
+        let mut context = OutputContext::new(&profile, &mut geo, &config, Writers::new(), &[]);
+        let mut summary = DetectionSummary::default();
+        let options = TimelineOptions::default();
+        let mut matched_correlation = Vec::new();
+        let engine = CorrelationEngine::new();
+        let mut state = ScanState::default();
+        let mut sizes = HashMap::new();
+        let mut poll = || {
+            scan_new_files(
+                &d,
+                &mut context,
+                &mut summary,
+                &options,
+                &Vec::new(),
+                &mut matched_correlation,
+                &engine,
+                &LogSource::Aws,
+                &mut state,
+                &mut sizes,
+            )
+        };
+        let record = |id: &str| serde_json::json!({"Records": [{"eventID": id}]}).to_string();
+
+        // A file is scanned at the second poll that sees it with the same size, and only once.
+        fs::write(dir.path().join("a.json"), record("a")).unwrap();
+        assert_eq!(poll(), 0);
+        assert_eq!(poll(), 1);
+        assert_eq!(poll(), 0);
+
+        // A file added later is picked up, but not while it is still growing.
+        let b = dir.path().join("b.json");
+        fs::write(&b, "{\"Records\": [").unwrap();
+        assert_eq!(poll(), 0);
+        fs::write(&b, record("b")).unwrap();
+        assert_eq!(poll(), 0);
+        assert_eq!(poll(), 1);
+        assert_eq!(poll(), 0);
+
+        // A file that cannot be read is not retried until it changes.
+        let c = dir.path().join("c.json.gz");
+        fs::write(&c, b"not gzip").unwrap();
+        assert_eq!(poll(), 0);
+        assert_eq!(poll(), 1);
+        assert_eq!(poll(), 0);
+        assert_eq!(poll(), 0);
+        write_gz(&c, record("c").as_bytes());
+        assert_eq!(poll(), 0);
+        assert_eq!(poll(), 1);
+        assert_eq!(poll(), 0);
+    }
+
+    #[test]
+    fn test_read_files_in_parallel_reads_files_at_the_same_time() {
+        use crate::core::object_store::Listing;
+        use std::sync::Condvar;
+        use std::time::Duration;
+
+        /// Each `get` waits (up to a timeout) until both objects are being read.
+        struct RendezvousStore {
+            started: Mutex<usize>,
+            both_started: Condvar,
+        }
+        impl ObjectStore for RendezvousStore {
+            fn url(&self, key: &str) -> String {
+                format!("s3://bucket/{key}")
+            }
+            fn list(&self, _: &str, _: bool) -> io::Result<Listing> {
+                Ok(Listing::default())
+            }
+            fn get(&self, key: &str) -> io::Result<Box<dyn Read + Send>> {
+                let mut started = self.started.lock().unwrap();
+                *started += 1;
+                self.both_started.notify_all();
+                let (started, timeout) = self
+                    .both_started
+                    .wait_timeout_while(started, Duration::from_secs(10), |n| *n < 2)
+                    .unwrap();
+                if timeout.timed_out() {
+                    return Err(io::Error::other(format!("{key} was read alone")));
+                }
+                drop(started);
+                let record = serde_json::json!({"Records": [{"eventID": key}]});
+                Ok(Box::new(io::Cursor::new(record.to_string().into_bytes())))
+            }
+        }
+
+        let store = RendezvousStore {
+            started: Mutex::new(0),
+            both_started: Condvar::new(),
+        };
+        let files: Vec<InputFile> = ["a.json", "b.json"]
+            .map(|key| {
+                InputFile::Remote(
+                    &store,
+                    RemoteObject {
+                        key: key.to_string(),
+                        size: 0,
+                    },
+                )
+            })
+            .into_iter()
+            .collect();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let mut messages = Vec::new();
+        pool.install(|| {
+            read_files_in_parallel(&files, &LogSource::Aws, false, |_, message| match message {
+                FileMessage::Events(events) => messages.push(events[0]["eventID"].clone()),
+                FileMessage::Failed(e) => messages.push(Value::String(e)),
+                _ => {}
+            })
+        });
+        assert_eq!(messages, vec!["a.json", "b.json"]);
+    }
+
+    #[test]
+    fn test_process_events_from_store_reads_matching_objects() {
+        use crate::core::object_store::tests::MemoryStore;
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use std::io::Write as _;
+
+        let record = |id: &str| serde_json::json!({"Records": [{"eventID": id}]}).to_string();
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(record("gz").as_bytes()).unwrap();
+        let gz = gz.finish().unwrap();
+        let root = "AWSLogs/123456789012/CloudTrail/us-east-1";
+        let (jan15, jan16, feb) = (record("jan15"), record("jan16"), record("feb"));
+        let store = MemoryStore::new(&[
+            (&format!("{root}/2024/01/15/a.json"), jan15.as_bytes()),
+            (&format!("{root}/2024/01/16/b.json.gz"), &gz),
+            (&format!("{root}/2024/01/16/c.json"), jan16.as_bytes()),
+            (&format!("{root}/2024/01/16/digest.txt"), b"not a log"),
+            (&format!("{root}/2024/02/01/d.json"), feb.as_bytes()),
+        ]);
+        let mut events = Vec::new();
+        process_events_from_store(
+            |e: &[Value]| events.extend(e.iter().map(|r| r["eventID"].clone())),
+            &store,
+            "AWSLogs/",
+            false,
+            true,
+            &LogSource::Aws,
+            &FileDateOption {
+                file_date_from: Some("20240116".to_string()),
+                file_date_to: Some("20240131".to_string()),
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(events, vec!["gz", "jan16"]);
+        // Days and months outside the range are never listed.
+        let listed = store.listed.lock().unwrap();
+        assert!(
+            !listed
+                .iter()
+                .any(|p| p.ends_with("/15/") || p.ends_with("/02/"))
+        );
+    }
+
+    #[test]
+    fn test_all_reads_each_source_like_its_own_loader() {
+        let fixtures = [
+            ("test_files/json/test.json", LogSource::Aws),
+            ("test_files/json/LookupEvents.json", LogSource::Aws),
+            (
+                "test_files/csv/cloudtrail_event_history.csv",
+                LogSource::Aws,
+            ),
+            (
+                "test_files/securitylake/cloudtrail_mgmt.zstd.parquet",
+                LogSource::Aws,
+            ),
+            ("test_files/json/azure_value_format.json", LogSource::Azure),
+            (
+                "test_files/json/azure_graph_api_format.json",
+                LogSource::Azure,
+            ),
+            ("test_files/json/gcp_audit_log.json", LogSource::Gcp),
+            ("test_files/json/okta_system_log.json", LogSource::Okta),
+            ("test_files/json/gws_login_activities.json", LogSource::Gws),
+            ("test_files/json/k8s_audit.jsonl", LogSource::Kubernetes),
+            ("test_files/vpcflow/vpc_flow_log.log", LogSource::VpcFlow),
+        ];
+        for (file, log) in fixtures {
+            let path = Path::new(file);
+            let expected = stream_events(path, &log).unwrap();
+            assert!(!expected.is_empty(), "{file}");
+            assert_eq!(
+                stream_events(path, &LogSource::All).unwrap(),
+                expected,
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_all_unwraps_records_from_mixed_sources() {
+        let ual = serde_json::json!({"CreationTime":"2024-01-15T03:04:05","Workload":"AzureActiveDirectory","Operation":"UserLoggedIn","AuditData":null});
+        let contents = format!(
+            "{}\n{}\n{}\n{}\n",
+            r#"{"eventVersion":"1.08","eventSource":"sts.amazonaws.com","eventName":"AssumeRoleWithSAML","eventTime":"2024-01-15T03:05:00Z"}"#,
+            ual,
+            r#"{"messageType":"DATA_MESSAGE","logEvents":[{"id":"1","message":"{\"eventSource\":\"s3.amazonaws.com\",\"eventTime\":\"2024-01-15T03:06:00Z\"}"}]}"#,
+            r#"{"category":"kube-audit","time":"2024-01-15T03:07:00Z","properties":{"log":"{\"kind\":\"Event\",\"apiVersion\":\"audit.k8s.io/v1\",\"auditID\":\"a1\",\"verb\":\"create\"}"}}"#,
+        );
+        let events = normalize_events(
+            read_records_from_str(&contents, "", &LogSource::All).unwrap(),
+            &LogSource::All,
+        );
+        let names: Vec<&str> = events
+            .iter()
+            .map(|e| {
+                e.get("eventSource")
+                    .or_else(|| e.get("Workload"))
+                    .or_else(|| e.get("auditID"))
+                    .and_then(Value::as_str)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "sts.amazonaws.com",
+                "AzureActiveDirectory",
+                "s3.amazonaws.com",
+                "a1"
+            ]
+        );
+        // Records of no known source are dropped.
+        assert!(all_records(serde_json::json!({"hello": "world"})).is_empty());
+    }
+
+    fn write_gz(path: &PathBuf, decompressed: &[u8]) {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use std::io::Write as _;
+        let mut enc = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        enc.write_all(decompressed).unwrap();
+        enc.finish().unwrap();
+    }
+
+    fn read_gz_file_capped(path: &PathBuf, max_bytes: u64) -> io::Result<String> {
+        let mut contents = String::new();
+        gzip_aware_reader(File::open(path)?, max_bytes)?.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+
+    #[test]
+    fn read_gz_file_capped_rejects_oversized_decompression() {
+        // 200 decompressed bytes with a 16-byte cap must error (not return partial data),
+        // so the caller stops reading the file instead of spending hours on a bomb.
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bomb.json.gz");
+        write_gz(&path, &[b'A'; 200]);
+        assert!(read_gz_file_capped(&path, 16).is_err());
     }
 
     #[test]
@@ -1047,9 +3048,12 @@ mod tests {
         if fs::write(&path, b"[]").is_err() {
             return;
         }
-        let (count, paths, _size) =
-            count_files_recursive(&dir.path().to_path_buf(), &FileDateOption::default())
-                .expect("non-UTF-8 filename must not panic the count walk");
+        let (count, paths, _size) = count_files_recursive(
+            &dir.path().to_path_buf(),
+            &FileDateOption::default(),
+            &LogSource::Aws,
+        )
+        .expect("non-UTF-8 filename must not panic the count walk");
         assert_eq!(count, 1);
         assert_eq!(paths.len(), 1);
     }
@@ -1074,6 +3078,8 @@ mod tests {
             true, // no_color
             &LogSource::Aws,
             &FileDateOption::default(),
+            None,
+            false,
         );
         assert!(
             result.is_ok(),
@@ -1108,6 +3114,8 @@ mod tests {
             true,
             &LogSource::Aws,
             &FileDateOption::default(),
+            None,
+            false,
         );
         assert!(result.is_ok());
         assert_eq!(
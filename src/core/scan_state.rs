@@ -0,0 +1,262 @@
+use crate::core::util::{FileDigest, sha256_file};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Version written to new state files. Files with a different version are rejected rather than
+/// misread.
+const STATE_VERSION: u32 = 1;
+
+/// What `--state-file` remembers between runs: the input files that were scanned (to the end, or
+/// up to a read error) and the correlation windows that were already reported, so a re-run only
+/// scans new or changed files and appends only new detections.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ScanState {
+    version: u32,
+    /// Fingerprints by canonical path.
+    #[serde(default)]
+    files: BTreeMap<String, FileFingerprint>,
+    /// Fingerprints of the files that could not be read to the end, by canonical path. The
+    /// detections up to the error were already reported, so such a file is only scanned again
+    /// once it changes.
+    #[serde(default)]
+    failed_files: BTreeMap<String, FileFingerprint>,
+    /// `rule|first event time|last event time` of each correlation already reported.
+    #[serde(default)]
+    correlation_windows: BTreeSet<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FileFingerprint {
+    pub size: u64,
+    /// Modification time in nanoseconds since the Unix epoch.
+    pub mtime: u128,
+    /// SHA-256 of the file contents, hex encoded.
+    pub hash: String,
+}
+
+impl Default for ScanState {
+    fn default() -> Self {
+        ScanState {
+            version: STATE_VERSION,
+            files: BTreeMap::new(),
+            failed_files: BTreeMap::new(),
+            correlation_windows: BTreeSet::new(),
+        }
+    }
+}
+
+impl ScanState {
+    /// Load the state at `path`, or start an empty one if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(ScanState::default()),
+            Err(e) => return Err(format!("Cannot read state file {}: {e}", path.display())),
+        };
+        let state: ScanState = serde_json::from_str(&data)
+            .map_err(|e| format!("State file {} is not valid: {e}", path.display()))?;
+        if state.version != STATE_VERSION {
+            return Err(format!(
+                "State file {} has unsupported version {}",
+                path.display(),
+                state.version
+            ));
+        }
+        Ok(state)
+    }
+
+    /// Write the state to `path`. It is written to a temporary file next to it first, so an
+    /// interrupted run never leaves a truncated state behind.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let err = |e: io::Error| format!("Cannot write state file {}: {e}", path.display());
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, json).map_err(err)?;
+        fs::rename(&tmp, path).map_err(err)
+    }
+
+    /// True if `path` was already scanned, to the end or up to a read error, and has not changed
+    /// since.
+    pub fn is_scanned(&mut self, path: &Path) -> bool {
+        let key = state_key(path);
+        is_unchanged(&mut self.files, &key, path)
+            || is_unchanged(&mut self.failed_files, &key, path)
+    }
+
+    /// Remember that `path` was scanned to the end. `before` is its size and mtime taken before
+    /// it was opened, and `digest` that of the bytes the scan read. If their sizes differ, the
+    /// file changed while it was read, so it is not recorded (and is scanned again next time);
+    /// returns whether it was recorded.
+    pub fn mark_scanned(&mut self, path: &Path, before: (u64, u128), digest: &FileDigest) -> bool {
+        let Some(fingerprint) = fingerprint(before, digest) else {
+            return false;
+        };
+        let key = state_key(path);
+        self.files.insert(key.clone(), fingerprint);
+        self.failed_files.remove(&key);
+        true
+    }
+
+    /// `mark_scanned` for a file that could not be read to the end, so it is not scanned again
+    /// (and its detections up to the error are not reported again) until it changes.
+    pub fn mark_failed(&mut self, path: &Path, before: (u64, u128), digest: &FileDigest) -> bool {
+        let Some(fingerprint) = fingerprint(before, digest) else {
+            return false;
+        };
+        let key = state_key(path);
+        self.failed_files.insert(key.clone(), fingerprint);
+        self.files.remove(&key);
+        true
+    }
+
+    /// Remember a reported correlation window, and return whether it is new.
+    pub fn add_correlation_window(&mut self, window: String) -> bool {
+        self.correlation_windows.insert(window)
+    }
+}
+
+/// The key of `path` in the state: its canonical path, so the same file matches whichever
+/// relative path or symlink it is reached by.
+fn state_key(path: &Path) -> String {
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| PathBuf::from(path))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// True if `files` has a fingerprint for `key` that still matches `path`. A file with the same
+/// size but a new mtime (e.g. copied again by a sync tool) is hashed, and counts as unchanged if
+/// its contents are the same.
+fn is_unchanged(files: &mut BTreeMap<String, FileFingerprint>, key: &str, path: &Path) -> bool {
+    let Some(known) = files.get(key) else {
+        return false;
+    };
+    let Ok((size, mtime)) = size_and_mtime(path) else {
+        return false;
+    };
+    if known.size != size {
+        return false;
+    }
+    if known.mtime == mtime {
+        return true;
+    }
+    match sha256_file(path) {
+        Ok(hash) if hash == known.hash => {
+            files.insert(key.to_string(), FileFingerprint { size, mtime, hash });
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The fingerprint of a file that was `(size, mtime)` before it was scanned, or `None` if the
+/// scan read a different number of bytes.
+fn fingerprint((size, mtime): (u64, u128), digest: &FileDigest) -> Option<FileFingerprint> {
+    (digest.size == size).then(|| FileFingerprint {
+        size,
+        mtime,
+        hash: digest.sha256.clone(),
+    })
+}
+
+/// The size of `path` and its modification time in nanoseconds since the Unix epoch.
+pub fn size_and_mtime(path: &Path) -> io::Result<(u64, u128)> {
+    let meta = fs::metadata(path)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    Ok((meta.len(), mtime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::util::file_digest;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+
+    /// Mark `path` as scanned, as if it had been read whole just now.
+    fn mark_scanned(state: &mut ScanState, path: &Path) -> bool {
+        let before = size_and_mtime(path).unwrap();
+        state.mark_scanned(path, before, &file_digest(path).unwrap())
+    }
+
+    #[test]
+    fn unchanged_files_are_skipped_and_changed_files_rescanned() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.json");
+        fs::write(&file, r#"{"Records":[]}"#).unwrap();
+        let mut state = ScanState::default();
+        assert!(!state.is_scanned(&file));
+        assert!(mark_scanned(&mut state, &file));
+        assert!(state.is_scanned(&file));
+
+        // Same contents with a new mtime still count as scanned.
+        let later = SystemTime::now() + Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(state.is_scanned(&file));
+
+        fs::write(&file, r#"{"Records":[{}]}"#).unwrap();
+        assert!(!state.is_scanned(&file));
+    }
+
+    #[test]
+    fn failed_files_are_skipped_until_they_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.json");
+        fs::write(&file, r#"{"Records":[{}, "#).unwrap();
+        let mut state = ScanState::default();
+        let before = size_and_mtime(&file).unwrap();
+        assert!(state.mark_failed(&file, before, &file_digest(&file).unwrap()));
+        assert!(state.is_scanned(&file));
+
+        fs::write(&file, r#"{"Records":[{}, {}]}"#).unwrap();
+        assert!(!state.is_scanned(&file));
+        assert!(mark_scanned(&mut state, &file));
+        assert!(state.is_scanned(&file));
+        assert!(state.failed_files.is_empty());
+    }
+
+    #[test]
+    fn files_that_grow_while_scanned_are_not_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.jsonl");
+        fs::write(&file, "{}\n").unwrap();
+        let before = size_and_mtime(&file).unwrap();
+        fs::write(&file, "{}\n{}\n").unwrap();
+        let mut state = ScanState::default();
+        assert!(!state.mark_scanned(&file, before, &file_digest(&file).unwrap()));
+        assert!(!state.is_scanned(&file));
+    }
+
+    #[test]
+    fn state_round_trips_through_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.json");
+        fs::write(&file, "{}").unwrap();
+        let state_path = dir.path().join("state.json");
+        let mut state = ScanState::load(&state_path).unwrap();
+        assert!(mark_scanned(&mut state, &file));
+        assert!(state.add_correlation_window("r|t1|t2".to_string()));
+        state.save(&state_path).unwrap();
+
+        let mut state = ScanState::load(&state_path).unwrap();
+        assert!(state.is_scanned(&file));
+        assert!(!state.add_correlation_window("r|t1|t2".to_string()));
+
+        fs::write(&state_path, r#"{"version":99}"#).unwrap();
+        assert!(ScanState::load(&state_path).is_err());
+    }
+}
@@ -0,0 +1,289 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+/// OCSF `API Activity` class, which Amazon Security Lake uses for CloudTrail management and data
+/// events.
+const API_ACTIVITY_CLASS_UID: u64 = 6003;
+
+/// CloudTrail field (dotted path) and the OCSF field (JSON pointer) Security Lake maps it to.
+/// Fields that have no OCSF equivalent are kept by Security Lake in `unmapped` and are copied
+/// back as-is.
+const FIELD_MAP: [(&str, &str); 25] = [
+    ("eventVersion", "/metadata/product/version"),
+    ("eventID", "/metadata/uid"),
+    ("eventName", "/api/operation"),
+    ("eventSource", "/api/service/name"),
+    ("apiVersion", "/api/version"),
+    ("requestID", "/api/request/uid"),
+    ("requestParameters", "/api/request/data"),
+    ("responseElements", "/api/response/data"),
+    ("errorCode", "/api/response/error"),
+    ("errorMessage", "/api/response/message"),
+    ("awsRegion", "/cloud/region"),
+    ("recipientAccountId", "/cloud/account/uid"),
+    ("sourceIPAddress", "/src_endpoint/ip"),
+    ("vpcEndpointId", "/src_endpoint/uid"),
+    ("userAgent", "/http_request/user_agent"),
+    ("userIdentity.type", "/actor/user/type"),
+    ("userIdentity.userName", "/actor/user/name"),
+    ("userIdentity.arn", "/actor/user/uid"),
+    ("userIdentity.principalId", "/actor/user/uid_alt"),
+    ("userIdentity.accountId", "/actor/user/account/uid"),
+    ("userIdentity.accessKeyId", "/actor/user/credential_uid"),
+    ("userIdentity.invokedBy", "/actor/invoked_by"),
+    (
+        "userIdentity.sessionContext.sessionIssuer.arn",
+        "/actor/session/issuer",
+    ),
+    (
+        "userIdentity.sessionContext.attributes.mfaAuthenticated",
+        "/actor/session/is_mfa",
+    ),
+    (
+        "userIdentity.sessionContext.attributes.creationDate",
+        "/actor/session/created_time_dt",
+    ),
+];
+
+/// True if `v` is an OCSF API Activity record produced from CloudTrail, as written by Amazon
+/// Security Lake (`class_uid: 6003`, `metadata.product.name: CloudTrail`).
+pub fn is_ocsf_cloudtrail(v: &Value) -> bool {
+    let class_uid = match v.get("class_uid") {
+        Some(Value::Number(n)) => n.as_u64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    };
+    class_uid == Some(API_ACTIVITY_CLASS_UID)
+        && v.pointer("/metadata/product/name")
+            .and_then(Value::as_str)
+            .is_some_and(|name| name.eq_ignore_ascii_case("CloudTrail"))
+}
+
+/// Format an OCSF timestamp as a CloudTrail `eventTime`. OCSF `time` is epoch milliseconds; the
+/// Parquet reader renders timestamp columns as `YYYY-MM-DD HH:MM:SS +00:00`, and the `*_dt`
+/// fields are RFC 3339.
+fn ocsf_time(v: &Value) -> Option<String> {
+    let time = match v {
+        Value::Number(n) => DateTime::from_timestamp_millis(n.as_i64()?)?,
+        Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .or_else(|_| DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S %:z"))
+            .ok()?
+            .with_timezone(&Utc),
+        _ => return None,
+    };
+    Some(time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Security Lake keeps `requestParameters`/`responseElements` and most `unmapped` values as JSON
+/// strings. Parse the ones holding an object or array so rules can match their nested fields.
+fn parse_embedded_json(v: &Value) -> Value {
+    match v {
+        Value::String(s) if s.starts_with('{') || s.starts_with('[') => {
+            serde_json::from_str(s).unwrap_or_else(|_| v.clone())
+        }
+        _ => v.clone(),
+    }
+}
+
+/// Insert `value` at a dotted CloudTrail path (e.g. `userIdentity.arn`), creating the
+/// intermediate objects.
+fn insert_path(map: &mut Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        Some((head, rest)) => {
+            let child = map
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !child.is_object() {
+                *child = Value::Object(Map::new());
+            }
+            if let Value::Object(child) = child {
+                insert_path(child, rest, value);
+            }
+        }
+        None => {
+            map.insert(path.to_string(), value);
+        }
+    }
+}
+
+/// Map an OCSF CloudTrail record from Security Lake back into the CloudTrail field names used by
+/// `config/aws_profile.yaml` and the Sigma rules (`eventName`, `eventSource`, `userIdentity.*`,
+/// ...). Any other record (including a plain CloudTrail event) is returned unchanged.
+pub fn ocsf_to_cloudtrail(v: Value) -> Value {
+    if !is_ocsf_cloudtrail(&v) {
+        return v;
+    }
+    let mut ct = Map::new();
+    // `unmapped` keys may be flattened dotted paths (e.g. `additionalEventData.SignatureVersion`).
+    // Copy them first so the explicitly mapped fields below take precedence.
+    if let Some(Value::Object(unmapped)) = v.get("unmapped") {
+        for (key, value) in unmapped {
+            insert_path(&mut ct, key, parse_embedded_json(value));
+        }
+    }
+    for (field, pointer) in FIELD_MAP {
+        if let Some(value) = v.pointer(pointer).filter(|v| !v.is_null()) {
+            insert_path(&mut ct, field, parse_embedded_json(value));
+        }
+    }
+    // Calls made by an AWS service have a service name instead of an IP address.
+    if !ct.contains_key("sourceIPAddress")
+        && let Some(domain) = v.pointer("/src_endpoint/domain").filter(|v| !v.is_null())
+    {
+        ct.insert("sourceIPAddress".to_string(), domain.clone());
+    }
+    if let Some(time) = v
+        .get("time")
+        .and_then(ocsf_time)
+        .or_else(|| v.get("time_dt").and_then(ocsf_time))
+    {
+        ct.insert("eventTime".to_string(), Value::String(time));
+    }
+    if let Some(Value::Array(resources)) = v.get("resources") {
+        let resources: Vec<Value> = resources
+            .iter()
+            .map(|r| {
+                let mut resource = Map::new();
+                for (field, pointer) in [
+                    ("ARN", "/uid"),
+                    ("type", "/type"),
+                    ("accountId", "/account_uid"),
+                    ("accountId", "/owner/account/uid"),
+                ] {
+                    if let Some(value) = r.pointer(pointer).filter(|v| !v.is_null()) {
+                        resource.insert(field.to_string(), value.clone());
+                    }
+                }
+                Value::Object(resource)
+            })
+            .collect();
+        ct.insert("resources".to_string(), Value::Array(resources));
+    }
+    Value::Object(ct)
+}
+
+/// Read the rows of a Parquet file (as delivered by Amazon Security Lake) one at a time, passing
+/// each to `emit` as a JSON object. Snappy, ZSTD and gzip compressed column chunks are supported.
+pub fn read_parquet_file(path: &Path, emit: &mut dyn FnMut(Value)) -> Result<(), Box<dyn Error>> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    for row in reader.get_row_iter(None)? {
+        emit(row?.to_json_value());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ocsf_record() -> Value {
+        json!({
+            "class_uid": 6003,
+            "time": 1705287845000_i64,
+            "metadata": {
+                "uid": "4a1b2c3d-0000-1111-2222-333344445555",
+                "product": {"name": "CloudTrail", "vendor_name": "AWS", "version": "1.09"}
+            },
+            "api": {
+                "operation": "StopLogging",
+                "service": {"name": "cloudtrail.amazonaws.com"},
+                "request": {"uid": "req-1", "data": "{\"name\":\"management-events\"}"},
+                "response": {"error": "AccessDenied", "message": "not authorized"}
+            },
+            "actor": {
+                "user": {
+                    "type": "AssumedRole",
+                    "uid": "arn:aws:sts::123456789012:assumed-role/Admin/alice",
+                    "uid_alt": "AROAEXAMPLE:alice",
+                    "account": {"uid": "123456789012"},
+                    "credential_uid": "ASIAEXAMPLE"
+                },
+                "session": {"issuer": "arn:aws:iam::123456789012:role/Admin", "is_mfa": false}
+            },
+            "src_endpoint": {"ip": "203.0.113.10"},
+            "http_request": {"user_agent": "aws-cli/2.15.0"},
+            "cloud": {"region": "us-east-1", "account": {"uid": "123456789012"}},
+            "resources": [{"uid": "arn:aws:cloudtrail:us-east-1:123456789012:trail/management-events",
+                           "type": "AWS::CloudTrail::Trail", "account_uid": "123456789012"}],
+            "unmapped": {"readOnly": "false", "eventType": "AwsApiCall",
+                         "additionalEventData.SignatureVersion": "SigV4"}
+        })
+    }
+
+    #[test]
+    fn maps_ocsf_api_activity_to_cloudtrail_fields() {
+        let ct = ocsf_to_cloudtrail(ocsf_record());
+        assert_eq!(ct["eventName"], "StopLogging");
+        assert_eq!(ct["eventSource"], "cloudtrail.amazonaws.com");
+        assert_eq!(ct["eventTime"], "2024-01-15T03:04:05Z");
+        assert_eq!(ct["eventID"], "4a1b2c3d-0000-1111-2222-333344445555");
+        assert_eq!(ct["awsRegion"], "us-east-1");
+        assert_eq!(ct["sourceIPAddress"], "203.0.113.10");
+        assert_eq!(ct["userAgent"], "aws-cli/2.15.0");
+        assert_eq!(ct["errorCode"], "AccessDenied");
+        assert_eq!(ct["userIdentity"]["type"], "AssumedRole");
+        assert_eq!(
+            ct["userIdentity"]["arn"],
+            "arn:aws:sts::123456789012:assumed-role/Admin/alice"
+        );
+        assert_eq!(ct["userIdentity"]["principalId"], "AROAEXAMPLE:alice");
+        assert_eq!(ct["userIdentity"]["accessKeyId"], "ASIAEXAMPLE");
+        assert_eq!(
+            ct["userIdentity"]["sessionContext"]["sessionIssuer"]["arn"],
+            "arn:aws:iam::123456789012:role/Admin"
+        );
+        // JSON strings are parsed so nested request parameters are matchable.
+        assert_eq!(ct["requestParameters"]["name"], "management-events");
+        assert_eq!(ct["resources"][0]["type"], "AWS::CloudTrail::Trail");
+        // `unmapped` fields come back, with dotted keys nested.
+        assert_eq!(ct["eventType"], "AwsApiCall");
+        assert_eq!(ct["additionalEventData"]["SignatureVersion"], "SigV4");
+    }
+
+    #[test]
+    fn service_calls_use_the_endpoint_domain_as_source() {
+        let mut record = ocsf_record();
+        record["src_endpoint"] = json!({"domain": "cloudformation.amazonaws.com"});
+        let ct = ocsf_to_cloudtrail(record);
+        assert_eq!(ct["sourceIPAddress"], "cloudformation.amazonaws.com");
+    }
+
+    #[test]
+    fn parquet_timestamp_strings_are_converted() {
+        assert_eq!(
+            ocsf_time(&json!("2024-01-15 03:04:05 +00:00")).unwrap(),
+            "2024-01-15T03:04:05Z"
+        );
+        assert_eq!(
+            ocsf_time(&json!("2024-01-15T12:04:05+09:00")).unwrap(),
+            "2024-01-15T03:04:05Z"
+        );
+    }
+
+    #[test]
+    fn non_cloudtrail_records_are_untouched() {
+        let ct = json!({"eventName": "StopLogging", "eventSource": "cloudtrail.amazonaws.com"});
+        assert_eq!(ocsf_to_cloudtrail(ct.clone()), ct);
+        // Other Security Lake sources (e.g. VPC Flow Logs, class 4001) are not API activity.
+        let flow = json!({"class_uid": 4001, "metadata": {"product": {"name": "Amazon VPC"}}});
+        assert!(!is_ocsf_cloudtrail(&flow));
+    }
+
+    #[test]
+    fn reads_security_lake_parquet_file() {
+        let path = Path::new("test_files/securitylake/cloudtrail_mgmt.zstd.parquet");
+        let mut rows = Vec::new();
+        read_parquet_file(path, &mut |row| rows.push(row)).unwrap();
+        assert_eq!(rows.len(), 2);
+        let ct = ocsf_to_cloudtrail(rows[0].clone());
+        assert_eq!(ct["eventName"], "StopLogging");
+        assert_eq!(ct["eventSource"], "cloudtrail.amazonaws.com");
+        assert_eq!(ct["userIdentity"]["type"], "AssumedRole");
+        assert_eq!(ct["eventTime"], "2024-01-15T03:04:05Z");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::core::color::SuzakuColor::{Cyan, Green, Orange, Red, White, Yellow};
 use crate::core::color::{SuzakuColor, rgb};
+use crate::core::dedup::SeenEvents;
 use crate::core::util::p;
 use chrono::{DateTime, Utc};
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
@@ -18,6 +19,10 @@ pub struct DetectionSummary {
     pub level_with_hits: HashMap<String, HashMap<String, usize>>,
     pub first_event_time: Option<DateTime<Utc>>,
     pub last_event_time: Option<DateTime<Utc>>,
+    /// Keys of the events scanned so far, with `--dedup`.
+    pub seen_events: Option<SeenEvents>,
+    /// Events dropped by `--dedup` as copies of an earlier event.
+    pub duplicate_events: usize,
 }
 
 pub fn print_summary(sum: &DetectionSummary, no_color: bool) {
@@ -86,6 +91,14 @@ fn print_summary_header(sum: &DetectionSummary, no_color: bool) {
     );
     p(None, ")", false);
     println!();
+    if sum.seen_events.is_some() {
+        p(Green.rdg(no_color), "Duplicate events dropped: ", false);
+        p(
+            None,
+            &sum.duplicate_events.to_formatted_string(&Locale::en),
+            true,
+        );
+    }
 }
 
 fn print_summary_levels(sum: &DetectionSummary, levels: &Vec<(&str, SuzakuColor)>) {
@@ -1,23 +1,36 @@
-use crate::core::color::SuzakuColor::{Green, Red};
+use crate::core::color::SuzakuColor::{Green, Orange, Red};
+use crate::core::dedup::SeenEvents;
 use crate::core::log_source::LogSource;
+use crate::core::manifest;
 use crate::core::rules;
-use crate::core::scan::{append_summary_data, scan_directory, scan_file};
+use crate::core::scan::{
+    append_summary_data, correlation_seq, next_correlation_seq, records_inputs, remote_input_url,
+    scan_directory, scan_file, scan_new_files, scan_remote,
+};
+use crate::core::scan_state::ScanState;
 use crate::core::summary::{DetectionSummary, print_detected_rule_authors, print_summary};
 use crate::core::timeline_writer::{
-    OutputConfig, OutputContext, init_writers, write_correlation_record, write_record,
+    OutputConfig, OutputContext, event_time, init_writers, write_correlation_record, write_record,
 };
 use crate::core::util::{fatal_error, load_profile, output_path_info, p};
 use crate::option::cli::{CommonOptions, TimelineOptions};
 use crate::option::geoip::GeoIPSearch;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use num_format::{Locale, ToFormattedString};
 use serde_json::Value;
 use sigma_rust::{CorrelationEngine, Rule, TimestampedEvent, parse_rules_from_yaml};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 use terminal_size::{Width, terminal_size};
 
+/// How often `--follow` checks the directory for new files.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
 pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log: LogSource) {
     let no_color = common_opt.no_color;
+    let started = Utc::now();
     let mut geo_search = None;
     if let Some(path) = options.output_opt.geo_ip.as_ref() {
         let res = GeoIPSearch::new(path);
@@ -35,20 +48,51 @@ pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log:
     let profile = load_profile(&log, &geo_search, false);
     let rules: Vec<Rule> = rules::load_rules_from_dir(&options.rules, &log);
     // Skip rules listed in <rules-dir>/config/<log>_ignore_rule_list.txt (superseded/duplicate
-    // rules that stay in the repo but should not be loaded).
-    let ignore_ids =
-        rules::load_ignore_rule_ids(&rules::ignore_rule_list_path(&options.rules, &log));
+    // rules that stay in the repo but should not be loaded), and in the user's --exclude-rules
+    // list, which lives outside the rules checkout so update-rules does not reset it.
+    let ignore_ids = rules::load_log_ignore_rule_ids(&options.rules, &log);
+    let exclude_ids = options
+        .exclude_rules
+        .as_ref()
+        .map_or_else(HashSet::new, |path| {
+            rules::read_rule_id_list(path).unwrap_or_else(|e| fatal_error(no_color, &e))
+        });
+    let include_ids = options
+        .include_rules
+        .as_ref()
+        .map(|path| rules::read_rule_id_list(path).unwrap_or_else(|e| fatal_error(no_color, &e)));
+    let level_overrides = options.level_override.as_ref().map(|path| {
+        rules::read_level_overrides(path).unwrap_or_else(|e| fatal_error(no_color, &e))
+    });
     let loaded_rule_count = rules.len();
     let rules: Vec<Rule> = rules::filter_ignored_rules(rules, &ignore_ids);
     let ignored_rule_count = loaded_rule_count - rules.len();
+    let count = rules.len();
+    let rules: Vec<Rule> = rules::filter_ignored_rules(rules, &exclude_ids);
+    let mut excluded_count = count - rules.len();
+    let count = rules.len();
+    let rules = rules::filter_included_rules(rules, include_ids.as_ref());
+    let mut not_included_count = count - rules.len();
+    let count = rules.len();
+    let rules = rules::filter_rules_by_tags(rules, &options.include_tag, &options.exclude_tag);
+    let mut tag_filtered_count = count - rules.len();
+    let count = rules.len();
+    let mut rules =
+        rules::filter_rules_by_status(rules, &options.include_status, &options.exclude_status);
+    let mut status_filtered_count = count - rules.len();
+    let overridden_level_count = level_overrides.as_ref().map_or(0, |overrides| {
+        rules::apply_level_overrides(&mut rules, overrides)
+    });
     let rules = rules::filter_rules_by_level(&rules, &options.min_level);
     let correlation_rules = rules::load_correlation_yamls_from_dir(&options.rules);
+    let max_timespan = rules::max_correlation_timespan(&correlation_rules);
     if rules.is_empty() && correlation_rules.is_empty() {
-        p(
-            Red.rdg(no_color),
-            "Suzaku could not load any rules. Please download the rules with the update-rules command.\n",
-            true,
-        );
+        let message = if loaded_rule_count > 0 {
+            "No rules are left after filtering. Please check the rule filter options.\n"
+        } else {
+            "Suzaku could not load any rules. Please download the rules with the update-rules command.\n"
+        };
+        p(Red.rdg(no_color), message, true);
         return;
     }
     let mut correlation_engine = CorrelationEngine::new();
@@ -67,9 +111,11 @@ pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log:
                         ignored_correlation_count += 1;
                         continue;
                     }
-                    if let Some(ref service) = rule.logsource.service
-                        && log.supported_services().contains(&service.as_str())
-                    {
+                    if rules::is_ignored(rule.id.as_deref(), &exclude_ids) {
+                        excluded_count += 1;
+                        continue;
+                    }
+                    if log.is_supported_rule(&rule) {
                         correlation_engine.add_base_rule(name, rule);
                         added_base_rules += 1;
                     }
@@ -79,6 +125,31 @@ pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log:
                         ignored_correlation_count += 1;
                         continue;
                     }
+                    if rules::is_ignored(rule.id.as_deref(), &exclude_ids) {
+                        excluded_count += 1;
+                        continue;
+                    }
+                    if !rules::is_included(rule.id.as_deref(), include_ids.as_ref()) {
+                        not_included_count += 1;
+                        continue;
+                    }
+                    if !rules::is_selected_by_tags(
+                        rule.tags.as_deref(),
+                        &options.include_tag,
+                        &options.exclude_tag,
+                    ) {
+                        tag_filtered_count += 1;
+                        continue;
+                    }
+                    let status = rule.status.as_ref().map(rules::status_name);
+                    if !rules::is_selected_by_status(
+                        status.as_deref(),
+                        &options.include_status,
+                        &options.exclude_status,
+                    ) {
+                        status_filtered_count += 1;
+                        continue;
+                    }
                     if added_base_rules == total_base_rules {
                         correlation_engine.add_correlation_rule(rule);
                         total_correlation_rules += 1;
@@ -107,6 +178,38 @@ pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log:
         );
         p(None, total_ignored.to_string().as_str(), true);
     }
+    if excluded_count > 0 {
+        p(
+            Green.rdg(no_color),
+            "Rules skipped via --exclude-rules: ",
+            false,
+        );
+        p(None, excluded_count.to_string().as_str(), true);
+    }
+    if not_included_count > 0 {
+        p(
+            Green.rdg(no_color),
+            "Rules skipped via --include-rules: ",
+            false,
+        );
+        p(None, not_included_count.to_string().as_str(), true);
+    }
+    if tag_filtered_count > 0 {
+        p(Green.rdg(no_color), "Rules skipped via tag filter: ", false);
+        p(None, tag_filtered_count.to_string().as_str(), true);
+    }
+    if status_filtered_count > 0 {
+        p(
+            Green.rdg(no_color),
+            "Rules skipped via status filter: ",
+            false,
+        );
+        p(None, status_filtered_count.to_string().as_str(), true);
+    }
+    if overridden_level_count > 0 {
+        p(Green.rdg(no_color), "Rule levels overridden: ", false);
+        p(None, overridden_level_count.to_string().as_str(), true);
+    }
     p(Green.rdg(no_color), "Total correlation rules: ", false);
     p(
         None,
@@ -114,15 +217,40 @@ pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log:
         true,
     );
 
+    if records_inputs(options) {
+        manifest::start_recording();
+    } else if options.follow && options.output_opt.output.is_some() {
+        // --follow only stops on Ctrl+C, so the run never reaches the point where the manifest
+        // is written.
+        p(
+            Orange.rdg(no_color),
+            "No run manifest is written with --follow.",
+            true,
+        );
+    }
+    let mut state = options
+        .state_file
+        .as_ref()
+        .map(|path| ScanState::load(path).unwrap_or_else(|e| fatal_error(no_color, &e)));
+    // --follow tracks the files it has scanned even without a state file.
+    if options.follow && state.is_none() {
+        state = Some(ScanState::default());
+    }
+    // A re-run with --state-file adds its new detections to the earlier output.
+    let append = state.is_some() && !options.output_opt.clobber;
     let (writers, output_pathes) = init_writers(
         options.output_opt.output.as_ref(),
         options.output_opt.output_type,
+        append,
     )
     .unwrap_or_else(|e| fatal_error(no_color, &e));
     let config = OutputConfig::new(no_color, options.output_opt.raw_output, options.localtime);
     let mut context =
         OutputContext::new(&profile, &mut geo_search, &config, writers, &output_pathes);
     let mut summary = DetectionSummary::default();
+    if options.dedup {
+        summary.seen_events = Some(SeenEvents::default());
+    }
     let mut matched_correlation: Vec<TimestampedEvent> = Vec::new();
     context.write_header();
 
@@ -136,6 +264,17 @@ pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log:
             &mut matched_correlation,
             &correlation_engine,
             &log,
+            state.as_mut(),
+        );
+    } else if remote_input_url(&options.input_opt).is_some() {
+        scan_remote(
+            &mut context,
+            &mut summary,
+            options,
+            &rules,
+            &mut matched_correlation,
+            &correlation_engine,
+            &log,
         );
     } else if let Some(f) = &options.input_opt.filepath {
         scan_file(
@@ -147,6 +286,7 @@ pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log:
             &mut matched_correlation,
             &correlation_engine,
             &log,
+            state.as_mut(),
         );
     }
 
@@ -155,9 +295,36 @@ pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log:
         &mut summary,
         &mut matched_correlation,
         &correlation_engine,
+        state.as_mut(),
+        0,
     );
 
+    if options.follow
+        && let (Some(d), Some(state)) = (&options.input_opt.directory, state.as_mut())
+    {
+        follow_directory(
+            d,
+            &mut context,
+            &mut summary,
+            options,
+            &rules,
+            &mut matched_correlation,
+            &correlation_engine,
+            &log,
+            state,
+            max_timespan,
+        );
+    }
+
     context.flush_all();
+    if let Some(state) = &state {
+        save_state(options, state, no_color);
+    }
+    if let Some(output) = &options.output_opt.output
+        && records_inputs(options)
+    {
+        write_run_manifest(options, output, &output_pathes, started, no_color);
+    }
     println!();
     let terminal_width = match terminal_size() {
         Some((Width(w), _)) => w as usize,
@@ -192,18 +359,149 @@ pub fn make_timeline(options: &TimelineOptions, common_opt: &CommonOptions, log:
     }
 }
 
+/// `--follow`: after the initial scan, poll `d` for new log files and write their detections,
+/// and the correlations they complete, as they arrive. Runs until the process is interrupted.
+///
+/// Matched events are kept only as long as a correlation may still need them (`max_timespan`
+/// before the newest one), and only correlations that include an event from the new files are
+/// reported, so memory use stays bounded and a sliding window is not reported again.
+#[allow(clippy::too_many_arguments)]
+fn follow_directory<'a>(
+    d: &PathBuf,
+    context: &mut OutputContext<'a>,
+    summary: &mut DetectionSummary,
+    options: &TimelineOptions,
+    rules: &Vec<&Rule>,
+    matched_correlation: &mut Vec<TimestampedEvent<'a>>,
+    correlation_engine: &'a CorrelationEngine,
+    log: &LogSource,
+    state: &mut ScanState,
+    max_timespan: Option<TimeDelta>,
+) -> ! {
+    let no_color = context.config.no_color;
+    context.flush();
+    save_state(options, state, no_color);
+    trim_matched_correlation(matched_correlation, max_timespan);
+    p(
+        Orange.rdg(no_color),
+        &format!(
+            "Watching {} for new log files. Press Ctrl+C to stop.",
+            d.display()
+        ),
+        true,
+    );
+    let mut sizes = HashMap::new();
+    loop {
+        thread::sleep(FOLLOW_POLL_INTERVAL);
+        let first_new = next_correlation_seq();
+        let scanned = scan_new_files(
+            d,
+            context,
+            summary,
+            options,
+            rules,
+            matched_correlation,
+            correlation_engine,
+            log,
+            state,
+            &mut sizes,
+        );
+        if scanned > 0 {
+            process_correlation_events(
+                context,
+                summary,
+                matched_correlation,
+                correlation_engine,
+                Some(state),
+                first_new,
+            );
+            trim_matched_correlation(matched_correlation, max_timespan);
+            context.flush();
+            save_state(options, state, no_color);
+        }
+    }
+}
+
+/// Drop the matched events more than `max_timespan` older than the newest one, which no
+/// correlation window can include together with a later event. If no correlation has a timespan
+/// that parses, nothing is kept.
+fn trim_matched_correlation(
+    matched_correlation: &mut Vec<TimestampedEvent>,
+    max_timespan: Option<TimeDelta>,
+) {
+    let Some(newest) = matched_correlation.iter().map(|e| e.timestamp).max() else {
+        return;
+    };
+    match max_timespan.and_then(|timespan| newest.checked_sub_signed(timespan)) {
+        Some(oldest) => matched_correlation.retain(|e| e.timestamp >= oldest),
+        None => matched_correlation.clear(),
+    }
+}
+
+/// Write the run manifest next to `output`. A failure is reported but does not fail the run,
+/// since the timeline itself was written.
+fn write_run_manifest(
+    options: &TimelineOptions,
+    output: &Path,
+    output_pathes: &[PathBuf],
+    started: DateTime<Utc>,
+    no_color: bool,
+) {
+    let manifest = manifest::build_manifest(&options.rules, output_pathes, started);
+    let result = manifest::write_manifest(&manifest, output);
+    match result {
+        Ok(path) => {
+            p(Green.rdg(no_color), "Run manifest: ", false);
+            p(None, &path.display().to_string(), true);
+        }
+        Err(e) => p(
+            Red.rdg(no_color),
+            &format!("Cannot write the run manifest: {e}"),
+            true,
+        ),
+    }
+}
+
+fn save_state(options: &TimelineOptions, state: &ScanState, no_color: bool) {
+    if let Some(path) = &options.state_file
+        && let Err(e) = state.save(path)
+    {
+        p(Red.rdg(no_color), &e, true);
+    }
+}
+
 fn process_correlation_events(
     context: &mut OutputContext,
     summary: &mut DetectionSummary,
     matched_correlation: &mut Vec<TimestampedEvent>,
     correlation_engine: &CorrelationEngine,
+    mut state: Option<&mut ScanState>,
+    first_new: u64,
 ) -> bool {
+    // The events numbered before `first_new` were correlated in an earlier pass (`--follow`); a
+    // result made of those alone was already reported or never matched.
+    let is_earlier = |e: &TimestampedEvent| correlation_seq(e).is_some_and(|seq| seq < first_new);
     let results = correlation_engine.process_events(matched_correlation);
     match results {
         Ok(results) => {
             for res in results.iter() {
                 let rule = res.rule;
-                if res.matched {
+                if res.matched && !res.events.iter().all(|e| is_earlier(e)) {
+                    // Skip the windows a previous --state-file run already reported, e.g. when a
+                    // changed file is scanned again.
+                    if let Some(state) = state.as_deref_mut()
+                        && let (Some(first), Some(last)) = (res.events.first(), res.events.last())
+                    {
+                        let window = format!(
+                            "{}|{}|{}",
+                            rule.id.as_deref().unwrap_or(&rule.title),
+                            first.timestamp.to_rfc3339(),
+                            last.timestamp.to_rfc3339()
+                        );
+                        if !state.add_correlation_window(window) {
+                            continue;
+                        }
+                    }
                     for event in &res.events {
                         let generate = rule.correlation.generate.unwrap_or(false);
                         if generate {
@@ -230,18 +528,17 @@ fn process_correlation_events(
                             .and_modify(|e| *e += 1)
                             .or_insert(1);
                         let event = &res.events.last().unwrap().event;
-                        if let Some(event_time) = event.get(context.prof_ts_key) {
-                            let event_time_str = event_time.value_to_string();
-                            if let Ok(event_time) = event_time_str.parse::<DateTime<Utc>>() {
-                                let date = event_time.date_naive().format("%Y-%m-%d").to_string();
-                                summary
-                                    .dates_with_hits
-                                    .entry(level)
-                                    .or_default()
-                                    .entry(date)
-                                    .and_modify(|e| *e += 1)
-                                    .or_insert(1);
-                            }
+                        if let Some(event_time_str) = event_time(event, context.prof_ts_key)
+                            && let Ok(event_time) = event_time_str.parse::<DateTime<Utc>>()
+                        {
+                            let date = event_time.date_naive().format("%Y-%m-%d").to_string();
+                            summary
+                                .dates_with_hits
+                                .entry(level)
+                                .or_default()
+                                .entry(date)
+                                .and_modify(|e| *e += 1)
+                                .or_insert(1);
                         }
                     }
                 }
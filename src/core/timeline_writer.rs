@@ -1,6 +1,6 @@
 use crate::core::color::SuzakuColor;
 use crate::core::color::SuzakuColor::{Green, Orange, Red, White, Yellow};
-use crate::core::util::{get_json_writer, get_writer, sanitize_csv_field};
+use crate::core::util::{get_appending_file, get_json_writer, get_writer, sanitize_csv_field};
 use crate::option::geoip::GeoIPSearch;
 use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
 use csv::Writer;
@@ -8,6 +8,7 @@ use itertools::Itertools;
 use serde_json::Value;
 use sigma_rust::{Event, Rule, SigmaCorrelationRule, TimestampedEvent};
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
 use std::io::{BufWriter, Write};
 use std::path::PathBuf;
 use termcolor::{BufferWriter, ColorChoice, ColorSpec, WriteColor};
@@ -47,11 +48,29 @@ fn format_timestamp(value: &str, localtime: bool) -> String {
     }
 }
 
+/// The value of the profile's `Timestamp` field (`prof_ts_key`) in `event`. The field may list
+/// alternatives (`.time|.eventTimestamp|.CreationTime`), of which the first present is used.
+pub fn event_time(event: &Event, prof_ts_key: &str) -> Option<String> {
+    event
+        .get(prof_ts_key)
+        .or_else(|| {
+            prof_ts_key
+                .split('|')
+                .map(|k| k.trim().trim_start_matches('.'))
+                .find_map(|k| event.get(k))
+        })
+        .map(|v| v.value_to_string())
+}
+
 pub struct Writers {
     csv: Option<Writer<Box<dyn Write>>>,
     json: Option<BufWriter<Box<dyn Write>>>,
     jsonl: Option<BufWriter<Box<dyn Write>>>,
     std: Option<BufferWriter>,
+    /// The output files are appended to, and kept even if nothing new is written.
+    append: bool,
+    /// The CSV file is appended to and already starts with a header.
+    csv_has_header: bool,
 }
 
 pub struct OutputContext<'a> {
@@ -319,7 +338,7 @@ fn mitre_tactics() -> &'static HashMap<String, String> {
 /// become `T1562.001`, and groups (`attack.g0035`) become `G0035`. Separators are normalized so
 /// both the hyphen (`attack.credential-access`) and underscore (`attack.credential_access`)
 /// spellings map to the same abbreviation. Unrecognized tags (e.g. `cve.*`) are returned unchanged.
-fn abbreviate_tag(tag: &str) -> String {
+pub(crate) fn abbreviate_tag(tag: &str) -> String {
     let lower = tag.to_lowercase();
     // Tactics: look up in the config-driven table, folding `_` to `-` to match its keys.
     if let Some(abbrev) = mitre_tactics().get(&lower.replace('_', "-")) {
@@ -444,6 +463,8 @@ fn get_value_from_event_common(
                     || k_trimmed.contains("time")
                     || k_trimmed.contains("eventTimestamp")
                     || k_trimmed.contains("CreationTime")
+                    || k_trimmed.contains("published")
+                    || k_trimmed.ends_with("Timestamp")
                 {
                     format_timestamp(&value.value_to_string(), localtime)
                 } else {
@@ -618,6 +639,8 @@ impl Writers {
             json: None,
             jsonl: None,
             std: None,
+            append: false,
+            csv_has_header: false,
         }
     }
 
@@ -666,7 +689,8 @@ impl<'a> OutputContext<'a> {
         }
     }
 
-    pub fn flush_all(&mut self) {
+    /// Flush what was written so far, keeping the writers open (for `--follow`).
+    pub fn flush(&mut self) {
         if let Some(ref mut writer) = self.writers.csv {
             writer.flush().unwrap();
         }
@@ -676,7 +700,11 @@ impl<'a> OutputContext<'a> {
         if let Some(ref mut writer) = self.writers.jsonl {
             writer.flush().unwrap();
         }
-        if !self.has_written {
+    }
+
+    pub fn flush_all(&mut self) {
+        self.flush();
+        if !self.has_written && !self.writers.append {
             self.writers.csv = None;
             self.writers.json = None;
             self.writers.jsonl = None;
@@ -696,7 +724,9 @@ impl<'a> OutputContext<'a> {
             writeln!(buf, "{}", csv_header.join(" · ")).ok();
         }
 
-        if let Some(ref mut writer) = self.writers.csv {
+        if let Some(ref mut writer) = self.writers.csv
+            && !self.writers.csv_has_header
+        {
             writer.write_record(&csv_header).unwrap();
         }
     }
@@ -723,12 +753,16 @@ impl OutputType {
         }
     }
 }
+/// Open the writers for `output_type`, or for stdout without `output_path`. With `append`, the
+/// output files are appended to instead of overwritten (for `--state-file` re-runs).
 pub fn init_writers(
     output_path: Option<&PathBuf>,
     output_type: u8,
+    append: bool,
 ) -> Result<(Writers, Vec<PathBuf>), String> {
     let mut output_pathes = vec![];
     let mut writers = Writers::new();
+    writers.append = append;
 
     if let Some(output_path) = output_path {
         let output_type = OutputType::from_u8(output_type).unwrap_or(OutputType::Csv);
@@ -740,7 +774,14 @@ pub fn init_writers(
                     csv_path.set_extension("csv");
                 }
                 output_pathes.push(csv_path.clone());
-                writers = writers.with_csv(get_writer(&Some(csv_path))?);
+                if append {
+                    writers.csv_has_header = fs::metadata(&csv_path).is_ok_and(|m| m.len() > 0);
+                    writers = writers.with_csv(Writer::from_writer(Box::new(get_appending_file(
+                        &csv_path,
+                    )?)));
+                } else {
+                    writers = writers.with_csv(get_writer(&Some(csv_path))?);
+                }
             }
             _ => {}
         }
@@ -752,7 +793,12 @@ pub fn init_writers(
                     json_path.set_extension("json");
                 }
                 output_pathes.push(json_path.clone());
-                writers = writers.with_json(get_json_writer(&Some(json_path))?);
+                if append {
+                    writers = writers
+                        .with_json(BufWriter::new(Box::new(get_appending_file(&json_path)?)));
+                } else {
+                    writers = writers.with_json(get_json_writer(&Some(json_path))?);
+                }
             }
             OutputType::Jsonl | OutputType::CsvAndJsonl => {
                 let mut jsonl_path = output_path.clone();
@@ -760,7 +806,12 @@ pub fn init_writers(
                     jsonl_path.set_extension("jsonl");
                 }
                 output_pathes.push(jsonl_path.clone());
-                writers = writers.with_jsonl(get_json_writer(&Some(jsonl_path))?);
+                if append {
+                    writers = writers
+                        .with_jsonl(BufWriter::new(Box::new(get_appending_file(&jsonl_path)?)));
+                } else {
+                    writers = writers.with_jsonl(get_json_writer(&Some(jsonl_path))?);
+                }
             }
             _ => {}
         }
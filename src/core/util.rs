@@ -3,9 +3,10 @@ use crate::core::log_source::LogSource;
 use crate::option::geoip::GeoIPSearch;
 use bytesize::ByteSize;
 use csv::Writer;
+use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 use termcolor::{BufferWriter, Color, ColorChoice, ColorSpec, WriteColor};
 
@@ -20,6 +21,83 @@ pub fn get_writer(output: &Option<PathBuf>) -> Result<Writer<Box<dyn Write>>, St
     Ok(wtr)
 }
 
+/// SHA-256 of the contents of `path`, hex encoded.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    file_digest(path).map(|digest| digest.sha256)
+}
+
+/// Size and SHA-256 (hex encoded) of an input file as stored, before decompression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileDigest {
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// The [`FileDigest`] of the contents of `path`.
+pub fn file_digest(path: &Path) -> io::Result<FileDigest> {
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(FileDigest {
+        size,
+        sha256: hex_digest(hasher),
+    })
+}
+
+/// A reader that hashes what is read through it, so an input file is hashed from the same bytes
+/// the scan parses rather than read again.
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    /// Read whatever the parser left unread (e.g. after a parse error), and return the digest of
+    /// the whole input, or `None` if it cannot be read to the end.
+    pub fn finish(mut self) -> Option<FileDigest> {
+        io::copy(&mut self, &mut io::sink()).ok()?;
+        Some(FileDigest {
+            size: self.size,
+            sha256: hex_digest(self.hasher),
+        })
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+}
+
+/// The digest of `hasher`, hex encoded.
+pub fn hex_digest(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Open `output` for appending, creating it if it does not exist yet.
+pub fn get_appending_file(output: &Path) -> Result<File, String> {
+    File::options()
+        .create(true)
+        .append(true)
+        .open(output)
+        .map_err(|e| format!("Cannot write to output file {}: {e}", output.display()))
+}
+
 pub fn get_json_writer(output: &Option<PathBuf>) -> Result<BufWriter<Box<dyn Write>>, String> {
     if let Some(output) = output {
         let file = File::create(output)
@@ -62,8 +140,13 @@ pub fn fatal_error(no_color: bool, msg: &str) -> ! {
     std::process::exit(1);
 }
 
+/// True if `path` is `-`, which `-f` takes to mean standard input.
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
 pub fn check_path_exists(filepath: Option<PathBuf>, dirpath: Option<PathBuf>) -> bool {
-    if let Some(file) = filepath {
+    if let Some(file) = filepath.filter(|f| !is_stdin(f)) {
         if !file.exists() {
             println!("File {file:?} does not exist.");
             return false;
@@ -209,6 +292,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn check_path_exists_accepts_stdin_dash() {
+        assert!(is_stdin(Path::new("-")));
+        assert!(!is_stdin(Path::new("./-")));
+        assert!(check_path_exists(Some(PathBuf::from("-")), None));
+        assert!(!check_path_exists(
+            Some(PathBuf::from("no_such_file.json")),
+            None
+        ));
+    }
+
     #[test]
     fn writers_default_to_stdout_when_no_output() {
         assert!(get_writer(&None).is_ok());
@@ -1,18 +1,30 @@
 use crate::cmd::aws::aws_metrics::aws_metrics;
+use crate::cmd::aws::aws_s3_timeline::aws_s3_timeline;
 use crate::cmd::aws::aws_search::aws_search;
 use crate::cmd::aws::aws_summary::aws_summary;
 use crate::cmd::aws::aws_timeline::aws_timeline;
+use crate::cmd::aws::aws_vpc_summary::aws_vpc_summary;
+use crate::cmd::aws::aws_vpc_timeline::aws_vpc_timeline;
 
 use crate::cmd::azure::azure_timeline::azure_timeline;
+use crate::cmd::cloud::cloud_timeline::cloud_timeline;
+use crate::cmd::gcp::gcp_timeline::gcp_timeline;
+use crate::cmd::gws::gws_timeline::gws_timeline;
+use crate::cmd::k8s::k8s_timeline::k8s_timeline;
+use crate::cmd::okta::okta_timeline::okta_timeline;
 use chrono::Local;
 use clap::{CommandFactory, Parser};
 use cmd::update::start_update_rules;
+use cmd::validate_rules::validate_rules;
 use core::color::SuzakuColor::Green;
+use core::log_source::LogSource;
 use core::util::{check_path_exists, p, set_rayon_threat_number};
 use libmimalloc_sys::mi_stats_print_out;
 use mimalloc::MiMalloc;
 use option::cli::Commands::{
-    AwsCtMetrics, AwsCtSearch, AwsCtSummary, AwsCtTimeline, AzureTimeline, UpdateRules,
+    AwsCtMetrics, AwsCtSearch, AwsCtSummary, AwsCtTimeline, AwsS3Search, AwsS3Timeline,
+    AwsVpcSearch, AwsVpcSummary, AwsVpcTimeline, AzureTimeline, CloudTimeline, GcpTimeline,
+    GwsTimeline, K8sTimeline, OktaTimeline, UpdateRules, ValidateRules,
 };
 use option::cli::{Cli, RELEASE_NAME, VERSION};
 use std::ptr::null_mut;
@@ -44,8 +56,19 @@ fn main() {
         | AwsCtSearch { common_opt, .. }
         | AwsCtMetrics { common_opt, .. }
         | AwsCtSummary { common_opt, .. }
+        | AwsVpcTimeline { common_opt, .. }
+        | AwsVpcSearch { common_opt, .. }
+        | AwsVpcSummary { common_opt, .. }
+        | AwsS3Timeline { common_opt, .. }
+        | AwsS3Search { common_opt, .. }
         | UpdateRules { common_opt }
-        | AzureTimeline { common_opt, .. } => common_opt.no_color,
+        | ValidateRules { common_opt, .. }
+        | AzureTimeline { common_opt, .. }
+        | GcpTimeline { common_opt, .. }
+        | OktaTimeline { common_opt, .. }
+        | GwsTimeline { common_opt, .. }
+        | K8sTimeline { common_opt, .. }
+        | CloudTimeline { common_opt, .. } => common_opt.no_color,
     };
 
     match cmd {
@@ -56,6 +79,34 @@ fn main() {
         | AwsCtTimeline {
             options,
             common_opt,
+        }
+        | AwsVpcTimeline {
+            options,
+            common_opt,
+        }
+        | AwsS3Timeline {
+            options,
+            common_opt,
+        }
+        | GcpTimeline {
+            options,
+            common_opt,
+        }
+        | OktaTimeline {
+            options,
+            common_opt,
+        }
+        | GwsTimeline {
+            options,
+            common_opt,
+        }
+        | K8sTimeline {
+            options,
+            common_opt,
+        }
+        | CloudTimeline {
+            options,
+            common_opt,
         } => {
             display_logo(common_opt.quiet, no_color, true, false);
             set_rayon_threat_number(options.output_opt.thread_num);
@@ -68,8 +119,10 @@ fn main() {
                 return;
             }
 
+            // With --state-file, new detections are appended to the existing output.
             if let Some(output) = &options.output_opt.output
                 && !options.output_opt.clobber
+                && options.state_file.is_none()
                 && output.exists()
             {
                 p(
@@ -112,12 +165,27 @@ fn main() {
             match cmd {
                 AzureTimeline { .. } => azure_timeline(options, common_opt),
                 AwsCtTimeline { .. } => aws_timeline(options, common_opt),
+                AwsVpcTimeline { .. } => aws_vpc_timeline(options, common_opt),
+                AwsS3Timeline { .. } => aws_s3_timeline(options, common_opt),
+                GcpTimeline { .. } => gcp_timeline(options, common_opt),
+                OktaTimeline { .. } => okta_timeline(options, common_opt),
+                GwsTimeline { .. } => gws_timeline(options, common_opt),
+                K8sTimeline { .. } => k8s_timeline(options, common_opt),
+                CloudTimeline { .. } => cloud_timeline(options, common_opt),
                 _ => unreachable!(),
             }
         }
         AwsCtSearch {
             options,
             common_opt,
+        }
+        | AwsVpcSearch {
+            options,
+            common_opt,
+        }
+        | AwsS3Search {
+            options,
+            common_opt,
         } => {
             display_logo(common_opt.quiet, no_color, true, false);
             set_rayon_threat_number(options.output_opt.thread_num);
@@ -157,7 +225,12 @@ fn main() {
                 return;
             }
 
-            aws_search(options, common_opt);
+            let log = match cmd {
+                AwsVpcSearch { .. } => LogSource::VpcFlow,
+                AwsS3Search { .. } => LogSource::S3Access,
+                _ => LogSource::Aws,
+            };
+            aws_search(options, common_opt, &log);
         }
         AwsCtMetrics {
             input_opt,
@@ -196,10 +269,42 @@ fn main() {
                 *clobber,
             );
         }
+        AwsVpcSummary {
+            input_opt,
+            output,
+            clobber,
+            common_opt,
+        } => {
+            display_logo(common_opt.quiet, no_color, true, false);
+            if !check_path_exists(input_opt.filepath.clone(), input_opt.directory.clone()) {
+                return;
+            }
+            if let Some(output) = output
+                && !clobber
+                && output.exists()
+            {
+                p(
+                    None,
+                    &format!(
+                        "The file {} already exists. Please specify a different filename or add the -C, --clobber option to overwrite.",
+                        output.display()
+                    ),
+                    true,
+                );
+                return;
+            }
+            aws_vpc_summary(input_opt, output, no_color);
+        }
         UpdateRules { common_opt } => {
             display_logo(common_opt.quiet, no_color, true, false);
             start_update_rules(no_color);
         }
+        ValidateRules { rules, common_opt } => {
+            display_logo(common_opt.quiet, no_color, true, false);
+            if !validate_rules(rules, no_color) {
+                std::process::exit(1);
+            }
+        }
     }
 
     // Print elapsed time
@@ -219,12 +324,35 @@ fn main() {
         | AwsCtSearch { common_opt, .. }
         | AwsCtMetrics { common_opt, .. }
         | AwsCtSummary { common_opt, .. }
+        | AwsVpcTimeline { common_opt, .. }
+        | AwsVpcSearch { common_opt, .. }
+        | AwsVpcSummary { common_opt, .. }
+        | AwsS3Timeline { common_opt, .. }
+        | AwsS3Search { common_opt, .. }
         | AzureTimeline { common_opt, .. }
-        | UpdateRules { common_opt } => common_opt.debug,
+        | GcpTimeline { common_opt, .. }
+        | OktaTimeline { common_opt, .. }
+        | GwsTimeline { common_opt, .. }
+        | K8sTimeline { common_opt, .. }
+        | CloudTimeline { common_opt, .. }
+        | UpdateRules { common_opt }
+        | ValidateRules { common_opt, .. } => common_opt.debug,
     };
 
     // Print issue reporting info for timeline commands
-    if matches!(cmd, AwsCtTimeline { .. } | AzureTimeline { .. }) && !debug {
+    if matches!(
+        cmd,
+        AwsCtTimeline { .. }
+            | AwsVpcTimeline { .. }
+            | AwsS3Timeline { .. }
+            | AzureTimeline { .. }
+            | GcpTimeline { .. }
+            | OktaTimeline { .. }
+            | GwsTimeline { .. }
+            | K8sTimeline { .. }
+            | CloudTimeline { .. }
+    ) && !debug
+    {
         print_issue_reporting_info(no_color);
     }
 
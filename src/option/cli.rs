@@ -1,3 +1,5 @@
+use crate::core::azure_blob::parse_container_url;
+use crate::core::s3::parse_s3_url;
 use crate::option::timefiler::parse_offset;
 use chrono::{DateTime, NaiveDate};
 use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand};
@@ -9,19 +11,44 @@ pub const RELEASE_NAME: &str = "BlackHat Arsenal 2026 Release";
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const FULL_VERSION: &str = concatcp!(VERSION, " ", RELEASE_NAME);
 
-/// Validate that the input is a valid date in YYYYMMDD format.
+/// The Sigma rule statuses `--include-status`/`--exclude-status` accept.
+const RULE_STATUSES: [&str; 5] = [
+    "stable",
+    "test",
+    "experimental",
+    "deprecated",
+    "unsupported",
+];
+
+/// Validate that the input is a valid date in YYYYMMDD format, or a date and hour in YYYYMMDDHH.
 fn parse_file_date(s: &str) -> Result<String, String> {
-    if s.len() != 8 || !s.chars().all(|c| c.is_ascii_digit()) {
-        return Err(format!("'{}' is not in YYYYMMDD format (e.g. 20240115)", s));
+    if !(s.len() == 8 || s.len() == 10) || !s.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!(
+            "'{}' is not in YYYYMMDD or YYYYMMDDHH format (e.g. 20240115, 2024011503)",
+            s
+        ));
     }
     let year: i32 = s[0..4].parse().unwrap();
     let month: u32 = s[4..6].parse().unwrap();
     let day: u32 = s[6..8].parse().unwrap();
     NaiveDate::from_ymd_opt(year, month, day)
         .ok_or_else(|| format!("'{}' is not a valid date", s))?;
+    if s.len() == 10 && s[8..10].parse::<u32>().unwrap() > 23 {
+        return Err(format!("'{}' is not a valid hour", s));
+    }
     Ok(s.to_string())
 }
 
+/// Validate `--s3` up front (e.g. `s3://bucket/AWSLogs/`).
+fn parse_s3_input(s: &str) -> Result<String, String> {
+    parse_s3_url(s).map(|_| s.to_string())
+}
+
+/// Validate `--azure-blob` up front (e.g. `https://account.blob.core.windows.net/insights-logs-signinlogs/`).
+fn parse_azure_blob_input(s: &str) -> Result<String, String> {
+    parse_container_url(s).map(|_| s.to_string())
+}
+
 /// Validate `--timeline-start` / `--timeline-end` up front so a malformed value is rejected at
 /// the CLI (with a clear message) instead of silently dropping every event during the scan.
 fn parse_time_bound(s: &str) -> Result<String, String> {
@@ -85,11 +112,11 @@ pub struct TimeOption {
 
 #[derive(Args, Clone, Debug, Default)]
 pub struct FileDateOption {
-    /// Filter files by start date based on AWSLogs S3 path date structure (ex: "20240101")
+    /// Filter files by start date (or hour) in the AWSLogs/Azure blob path (ex: "20240101", "2024010103")
     #[arg(help_heading = Some("Filtering"), long = "file-date-from", value_name = "DATE", value_parser = parse_file_date, display_order = 213)]
     pub file_date_from: Option<String>,
 
-    /// Filter files by end date based on AWSLogs S3 path date structure (ex: "20241231")
+    /// Filter files by end date (or hour) in the AWSLogs/Azure blob path (ex: "20241231", "2024123123")
     #[arg(help_heading = Some("Filtering"), long = "file-date-to", value_name = "DATE", value_parser = parse_file_date, display_order = 214)]
     pub file_date_to: Option<String>,
 }
@@ -147,16 +174,30 @@ pub struct SearchOptions {
 }
 
 #[derive(Args, Clone, Debug, Default)]
-#[clap(group(ArgGroup::new("input_filtering").args(["directory", "filepath"]).required(true)))]
+#[clap(group(ArgGroup::new("input_filtering").args(["directory", "filepath", "s3_url", "azure_blob_url"]).required(true)))]
 pub struct InputOption {
     /// Directory of multiple gz/json files
-    #[arg(help_heading = Some("Input"), short = 'd', long, value_name = "DIR", conflicts_with_all = ["filepath"], display_order = 100)]
+    #[arg(help_heading = Some("Input"), short = 'd', long, value_name = "DIR", conflicts_with_all = ["filepath", "s3_url", "azure_blob_url"], display_order = 100)]
     pub directory: Option<PathBuf>,
 
-    /// File path to one gz/json file
-    #[arg(help_heading = Some("Input"), short = 'f', long = "file", value_name = "FILE", conflicts_with_all = ["directory"], display_order = 101)]
+    /// File path to one gz/json file (use "-" to read from stdin)
+    #[arg(help_heading = Some("Input"), short = 'f', long = "file", value_name = "FILE", conflicts_with_all = ["directory", "s3_url", "azure_blob_url"], display_order = 101)]
     pub filepath: Option<PathBuf>,
 
+    /// Scan the objects under an S3 URL without downloading them first (ex: s3://bucket/AWSLogs/)
+    #[arg(help_heading = Some("Input"), long = "s3", value_name = "S3-URL", value_parser = parse_s3_input, conflicts_with_all = ["directory", "filepath", "azure_blob_url"], display_order = 102)]
+    pub s3_url: Option<String>,
+
+    #[clap(flatten)]
+    pub s3_opt: S3Option,
+
+    /// Scan the blobs under an Azure Blob Storage container URL, optionally with a SAS token as its query (ex: https://account.blob.core.windows.net/insights-logs-signinlogs/)
+    #[arg(help_heading = Some("Input"), long = "azure-blob", value_name = "CONTAINER-URL", value_parser = parse_azure_blob_input, conflicts_with_all = ["directory", "filepath", "s3_url"], display_order = 108)]
+    pub azure_blob_url: Option<String>,
+
+    #[clap(flatten)]
+    pub azure_blob_opt: AzureBlobOption,
+
     #[clap(flatten)]
     pub time_opt: TimeOption,
 
@@ -164,12 +205,60 @@ pub struct InputOption {
     pub file_date_opt: FileDateOption,
 }
 
+#[derive(Args, Clone, Debug, Default)]
+pub struct S3Option {
+    /// S3-compatible endpoint such as MinIO, addressed path style (default: AWS S3) (ex: http://localhost:9000)
+    #[arg(help_heading = Some("Input"), long = "s3-endpoint", value_name = "URL", env = "AWS_ENDPOINT_URL_S3", display_order = 103)]
+    pub endpoint: Option<String>,
+
+    /// Region of the S3 bucket (default: us-east-1)
+    #[arg(help_heading = Some("Input"), long = "s3-region", value_name = "REGION", env = "AWS_REGION", default_value = "us-east-1", hide_default_value = true, display_order = 104)]
+    pub region: String,
+
+    /// S3 access key ID (requests are sent unsigned without one)
+    #[arg(help_heading = Some("Input"), long = "s3-access-key", value_name = "KEY", env = "AWS_ACCESS_KEY_ID", hide_env_values = true, display_order = 105)]
+    pub access_key: Option<String>,
+
+    /// S3 secret access key
+    #[arg(help_heading = Some("Input"), long = "s3-secret-key", value_name = "SECRET", env = "AWS_SECRET_ACCESS_KEY", hide_env_values = true, display_order = 106)]
+    pub secret_key: Option<String>,
+
+    /// S3 session token for temporary credentials
+    #[arg(help_heading = Some("Input"), long = "s3-session-token", value_name = "TOKEN", env = "AWS_SESSION_TOKEN", hide_env_values = true, display_order = 107)]
+    pub session_token: Option<String>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct AzureBlobOption {
+    /// SAS token for the container, if not already in the container URL (requests are sent anonymously without a SAS token or account key)
+    #[arg(help_heading = Some("Input"), long = "azure-sas-token", value_name = "TOKEN", env = "AZURE_STORAGE_SAS_TOKEN", hide_env_values = true, display_order = 109)]
+    pub sas_token: Option<String>,
+
+    /// Storage account shared key (for Azurite: its well-known devstoreaccount1 key)
+    #[arg(help_heading = Some("Input"), long = "azure-account-key", value_name = "KEY", env = "AZURE_STORAGE_KEY", hide_env_values = true, display_order = 110)]
+    pub account_key: Option<String>,
+}
+
 #[derive(Args, Clone, Debug, Default)]
 pub struct TimelineOptions {
     /// Specify a custom rule directory or file (default: ./rules)
     #[arg(help_heading = Some("General Options"), short = 'r', long, default_value = "./rules", hide_default_value = true, value_name = "DIR/FILE", display_order = 11)]
     pub rules: PathBuf,
 
+    /// Remember the scanned files and reported correlations in a state file, so a re-run only scans new or changed files and appends new detections to the output
+    #[arg(help_heading = Some("General Options"), long = "state-file", value_name = "FILE", conflicts_with_all = ["s3_url", "azure_blob_url"], display_order = 12)]
+    pub state_file: Option<PathBuf>,
+
+    /// Keep watching the directory and scan new log files as they are written (stop with Ctrl+C)
+    // Not `requires = "directory"`: clap counts any member of the required input group as
+    // satisfying it, so `--follow -f FILE` was accepted.
+    #[arg(help_heading = Some("General Options"), long = "follow", conflicts_with_all = ["filepath", "s3_url", "azure_blob_url"], display_order = 13)]
+    pub follow: bool,
+
+    /// Drop duplicate events from overlapping exports (same CloudTrail eventID, or Azure id / correlationId + time)
+    #[arg(help_heading = Some("General Options"), long = "dedup", display_order = 14)]
+    pub dedup: bool,
+
     #[clap(flatten)]
     pub input_opt: InputOption,
 
@@ -180,6 +269,34 @@ pub struct TimelineOptions {
     #[arg(help_heading = Some("Display Settings"), short = 'N', long = "no-summary", display_order = 401)]
     pub no_summary: bool,
 
+    /// Only load rules with one of these tags, in full or abbreviated form (ex: attack.credential_access,DefEvas,T1098)
+    #[arg(help_heading = Some("Filtering"), long = "include-tag", value_name = "TAG...", value_delimiter = ',', display_order = 220)]
+    pub include_tag: Vec<String>,
+
+    /// Do not load rules with any of these tags, in full or abbreviated form (ex: attack.discovery,Recon)
+    #[arg(help_heading = Some("Filtering"), long = "exclude-tag", value_name = "TAG...", value_delimiter = ',', display_order = 221)]
+    pub exclude_tag: Vec<String>,
+
+    /// Only load rules with one of these statuses (deprecated and unsupported rules are not loaded unless listed here)
+    #[arg(help_heading = Some("Filtering"), long = "include-status", value_name = "STATUS...", value_delimiter = ',', value_parser = RULE_STATUSES, display_order = 222)]
+    pub include_status: Vec<String>,
+
+    /// Do not load rules with any of these statuses (ex: experimental)
+    #[arg(help_heading = Some("Filtering"), long = "exclude-status", value_name = "STATUS...", value_delimiter = ',', value_parser = RULE_STATUSES, display_order = 223)]
+    pub exclude_status: Vec<String>,
+
+    /// Only load the rules whose UUIDs are listed in this file (one per line, like the ignore lists)
+    #[arg(help_heading = Some("Filtering"), long = "include-rules", value_name = "FILE", display_order = 224)]
+    pub include_rules: Option<PathBuf>,
+
+    /// Do not load the rules whose UUIDs are listed in this file (one per line, like the ignore lists)
+    #[arg(help_heading = Some("Filtering"), long = "exclude-rules", value_name = "FILE", display_order = 225)]
+    pub exclude_rules: Option<PathBuf>,
+
+    /// Change rule levels with a file of "<rule UUID>,<level>" lines, before --min-level is applied
+    #[arg(help_heading = Some("Filtering"), long = "level-override", value_name = "FILE", display_order = 226)]
+    pub level_override: Option<PathBuf>,
+
     /// Minimum level for rules to load (default: informational)
     #[arg(help_heading = Some("Output"), short = 'm', long = "min-level", default_value = "informational", hide_default_value = true, value_name = "LEVEL", display_order = 302)]
     pub min_level: String,
@@ -293,6 +410,94 @@ pub enum Commands {
         common_opt: CommonOptions,
     },
 
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion: {version}\n{author-with-newline}\n{usage-heading}\n  suzaku aws-vpc-timeline <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Creates an AWS VPC Flow Logs DFIR timeline
+    AwsVpcTimeline {
+        #[clap(flatten)]
+        options: TimelineOptions,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion: {version}\n{author-with-newline}\n{usage-heading}\n  suzaku aws-vpc-search <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Search AWS VPC Flow Logs
+    AwsVpcSearch {
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+
+        #[clap(flatten)]
+        options: SearchOptions,
+    },
+
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion {version}\n{author-with-newline}\n{usage-heading}\n  suzaku aws-vpc-summary <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Summarizes AWS VPC Flow Logs traffic per source/destination
+    AwsVpcSummary {
+        #[clap(flatten)]
+        input_opt: InputOption,
+
+        /// Overwrite files when saving
+        #[arg(help_heading = Some("Output"), short = 'C', long = "clobber", display_order = 300)]
+        clobber: bool,
+
+        /// Output CSV
+        #[arg(help_heading = Some("Output"), short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion: {version}\n{author-with-newline}\n{usage-heading}\n  suzaku aws-s3-timeline <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Creates an AWS S3 server access log DFIR timeline
+    AwsS3Timeline {
+        #[clap(flatten)]
+        options: TimelineOptions,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion: {version}\n{author-with-newline}\n{usage-heading}\n  suzaku aws-s3-search <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Search AWS S3 server access logs
+    AwsS3Search {
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+
+        #[clap(flatten)]
+        options: SearchOptions,
+    },
+
     #[command(
         author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
         version = FULL_VERSION,
@@ -309,9 +514,125 @@ pub enum Commands {
         common_opt: CommonOptions,
     },
 
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion: {version}\n{author-with-newline}\n{usage-heading}\n  suzaku gcp-timeline <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Creates a GCP Cloud Audit Logs DFIR timeline
+    GcpTimeline {
+        #[clap(flatten)]
+        options: TimelineOptions,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion: {version}\n{author-with-newline}\n{usage-heading}\n  suzaku okta-timeline <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Creates an Okta System Log DFIR timeline
+    OktaTimeline {
+        #[clap(flatten)]
+        options: TimelineOptions,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion: {version}\n{author-with-newline}\n{usage-heading}\n  suzaku gws-timeline <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Creates a Google Workspace DFIR timeline
+    GwsTimeline {
+        #[clap(flatten)]
+        options: TimelineOptions,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion: {version}\n{author-with-newline}\n{usage-heading}\n  suzaku k8s-timeline <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Creates a Kubernetes audit log DFIR timeline
+    K8sTimeline {
+        #[clap(flatten)]
+        options: TimelineOptions,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+
+    #[command(
+        author = "Yamato Security (https://github.com/Yamato-Security/suzaku - @SecurityYamato)",
+        version = FULL_VERSION,
+        help_template = "\nVersion: {version}\n{author-with-newline}\n{usage-heading}\n  suzaku cloud-timeline <INPUT> [OPTIONS]\n\n{all-args}",
+        disable_help_flag = true,
+        disable_version_flag = true
+    )]
+    /// Creates a merged multi-cloud DFIR timeline (CloudTrail, Azure/M365, GCP, Okta, Google Workspace, Kubernetes, VPC Flow Logs, S3 server access logs)
+    CloudTimeline {
+        #[clap(flatten)]
+        options: TimelineOptions,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+
     #[command(about = "Update rules", disable_help_flag = true)]
     UpdateRules {
         #[clap(flatten)]
         common_opt: CommonOptions,
     },
+
+    #[command(
+        about = "Check rule files and report the ones that would not be loaded",
+        disable_help_flag = true
+    )]
+    ValidateRules {
+        /// Specify a custom rule directory or file (default: ./rules)
+        #[arg(
+            short = 'r',
+            long,
+            default_value = "./rules",
+            hide_default_value = true,
+            value_name = "DIR/FILE"
+        )]
+        rules: PathBuf,
+
+        #[clap(flatten)]
+        common_opt: CommonOptions,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_requires_a_directory() {
+        let parse = |input: &[&str]| {
+            let args = ["suzaku", "aws-ct-timeline", "--follow"];
+            Cli::try_parse_from(args.iter().chain(input))
+        };
+        assert!(parse(&["-d", "logs"]).is_ok());
+        assert!(parse(&["-f", "logs/a.json"]).is_err());
+        assert!(parse(&["--s3", "s3://bucket/AWSLogs/"]).is_err());
+        assert!(parse(&[]).is_err());
+    }
 }
@@ -4,11 +4,25 @@ use regex::Regex;
 use serde_json::Value;
 use std::sync::LazyLock;
 
+/// AWSLogs `.../YYYY/MM/DD/...`, and Event Hub capture `.../{PartitionId}/YYYY/MM/DD/HH/mm/ss.avro`
+/// where an hour directory follows the day.
 static DATE_PATH_RE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"[/\\](\d{4})[/\\](\d{2})[/\\](\d{2})[/\\]")
+    Regex::new(r"[/\\](\d{4})[/\\](\d{2})[/\\](\d{2})[/\\](?:(\d{2})[/\\])?")
         .expect("DATE_PATH_RE regex pattern is invalid")
 });
 
+/// Azure diagnostic settings write blobs as `resourceId=.../y=YYYY/m=MM/d=DD/h=HH/m=00/PT1H.json`.
+static AZURE_BLOB_PATH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[/\\]y=(\d{4})[/\\]m=(\d{2})[/\\]d=(\d{2})[/\\](?:h=(\d{2})[/\\])?")
+        .expect("AZURE_BLOB_PATH_RE regex pattern is invalid")
+});
+
+/// Amazon Security Lake partitions objects by day as `.../eventDay=YYYYMMDD/...`.
+static EVENT_DAY_PATH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[/\\]eventDay=(\d{4})(\d{2})(\d{2})[/\\]")
+        .expect("EVENT_DAY_PATH_RE regex pattern is invalid")
+});
+
 pub fn filter_by_time(opt: &TimeOption, value: &Value, ts_key: &str) -> bool {
     // With no time constraints, keep every event. Events whose timestamp field is
     // absent or in an unrecognized format must not be silently dropped just
@@ -22,9 +36,14 @@ pub fn filter_by_time(opt: &TimeOption, value: &Value, ts_key: &str) -> bool {
         .map(|k| k.trim_start_matches('.'))
         .collect();
 
-    let event_time_str = keys
-        .iter()
-        .find_map(|k| value.get(k.trim()).and_then(|v| v.as_str()));
+    let event_time_str = keys.iter().find_map(|k| {
+        let k = k.trim();
+        // Nested timestamps (e.g. Google Workspace `id.time`) are given as dotted paths.
+        value
+            .get(k)
+            .or_else(|| value.pointer(&format!("/{}", k.replace('.', "/"))))
+            .and_then(|v| v.as_str())
+    });
 
     let event_time_str = match event_time_str {
         Some(s) => s,
@@ -64,30 +83,76 @@ pub fn filter_by_time(opt: &TimeOption, value: &Value, ts_key: &str) -> bool {
     }
     true
 }
-/// Filter files by their path date structure (YYYY/MM/DD).
-/// Intended for AWSLogs S3-compatible paths like `AWSLogs/{account}/{service}/{region}/YYYY/MM/DD/`.
-/// If no date pattern is found in the path (e.g. Azure logs), the file is passed through (returns true).
+/// A listing prefix that ends in a partial date: `YYYY/`, `YYYY/MM/`, `YYYY/MM/DD/`,
+/// `YYYY/MM/DD/HH/`, or the Azure `y=YYYY/m=MM/d=DD/h=HH/` equivalents.
+static PARTIAL_DATE_PREFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"[/\\](?:y=)?((?:19|20)\d{2})[/\\](?:(?:m=)?(0[1-9]|1[0-2])[/\\](?:(?:d=)?(\d{2})[/\\](?:(?:h=)?(\d{2})[/\\])?)?)?$",
+    )
+    .expect("PARTIAL_DATE_PREFIX_RE regex pattern is invalid")
+});
+
+/// True if a path date `YYYY[MM[DD[HH]]]` can fall within the `--file-date-from`/`--file-date-to`
+/// bounds (`YYYYMMDD` or `YYYYMMDDHH`). The two are compared at the precision both have, so an
+/// hour bound keeps a day-only path of that day and vice versa.
+fn date_in_range(opt: &FileDateOption, date: &str) -> bool {
+    let common = |bound: &str| {
+        let len = date.len().min(bound.len());
+        (date[..len].to_string(), bound[..len].to_string())
+    };
+    if let Some(from) = &opt.file_date_from {
+        let (date, from) = common(from);
+        if date < from {
+            return false;
+        }
+    }
+    if let Some(to) = &opt.file_date_to {
+        let (date, to) = common(to);
+        if date > to {
+            return false;
+        }
+    }
+    true
+}
+
+/// Filter files by the date in their path: AWSLogs `YYYY/MM/DD/`, Security Lake `eventDay=YYYYMMDD/`,
+/// Azure diagnostic settings `y=YYYY/m=MM/d=DD/h=HH/` and Event Hub capture `YYYY/MM/DD/HH/`.
+/// Bounds may be given to the hour (`YYYYMMDDHH`).
+/// If no date pattern is found in the path, the file is passed through (returns true).
 pub fn filter_file_by_date_path(opt: &FileDateOption, path: &str) -> bool {
     if opt.file_date_from.is_none() && opt.file_date_to.is_none() {
         return true;
     }
-    let Some(caps) = DATE_PATH_RE.captures(path) else {
-        // No YYYY/MM/DD pattern found; pass through (e.g. Azure paths)
+    let Some(caps) = AZURE_BLOB_PATH_RE
+        .captures(path)
+        .or_else(|| EVENT_DAY_PATH_RE.captures(path))
+        .or_else(|| DATE_PATH_RE.captures(path))
+    else {
+        // No date pattern found; pass through
         return true;
     };
-    // Compose as YYYYMMDD for direct lexicographic comparison with user input
-    let file_date = format!("{}{}{}", &caps[1], &caps[2], &caps[3]);
-    if let Some(from) = &opt.file_date_from
-        && file_date.as_str() < from.as_str()
-    {
-        return false;
-    }
-    if let Some(to) = &opt.file_date_to
-        && file_date.as_str() > to.as_str()
-    {
-        return false;
+    // Compose as YYYYMMDD[HH] for direct lexicographic comparison with user input
+    let mut file_date = format!("{}{}{}", &caps[1], &caps[2], &caps[3]);
+    if let Some(hour) = caps.get(4) {
+        file_date.push_str(hour.as_str());
     }
-    true
+    date_in_range(opt, &file_date)
+}
+
+/// Whether anything under the listing prefix `prefix` (ending in `/`) can pass
+/// `filter_file_by_date_path`, so remote listings skip whole years, months, days or hours outside
+/// the `--file-date-from`/`--file-date-to` range instead of listing every object in them.
+pub fn filter_prefix_by_date_path(opt: &FileDateOption, prefix: &str) -> bool {
+    if opt.file_date_from.is_none() && opt.file_date_to.is_none() {
+        return true;
+    }
+    let Some(caps) = PARTIAL_DATE_PREFIX_RE.captures(prefix) else {
+        return filter_file_by_date_path(opt, prefix);
+    };
+    let date: String = (1..=4)
+        .filter_map(|i| caps.get(i).map(|m| m.as_str()))
+        .collect();
+    date_in_range(opt, &date)
 }
 
 /// Parse an event timestamp string across the formats seen in Azure/M365 logs:
@@ -237,6 +302,114 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_filter_file_security_lake_event_day_partition() {
+        let opt = FileDateOption {
+            file_date_from: Some("20240101".to_string()),
+            file_date_to: Some("20240110".to_string()),
+        };
+        let path = "aws/CLOUD_TRAIL_MGMT/2.0/region=us-east-1/accountId=123456789012/eventDay=%s/part.gz.parquet";
+        assert!(filter_file_by_date_path(
+            &opt,
+            &path.replace("%s", "20240105")
+        ));
+        assert!(!filter_file_by_date_path(
+            &opt,
+            &path.replace("%s", "20240115")
+        ));
+    }
+
+    #[test]
+    fn test_filter_file_azure_diagnostic_blob_path() {
+        let path = "insights-logs-signinlogs/resourceId=/tenants/abc/providers/microsoft.aadiam/y=2024/m=01/d=%d/h=%h/m=00/PT1H.json";
+        let blob = |d: &str, h: &str| path.replace("%d", d).replace("%h", h);
+        let opt = FileDateOption {
+            file_date_from: Some("20240115".to_string()),
+            file_date_to: Some("20240131".to_string()),
+        };
+        assert!(filter_file_by_date_path(&opt, &blob("15", "00")));
+        assert!(!filter_file_by_date_path(&opt, &blob("14", "23")));
+        // Hour-level bounds
+        let opt = FileDateOption {
+            file_date_from: Some("2024011503".to_string()),
+            file_date_to: Some("2024011505".to_string()),
+        };
+        assert!(!filter_file_by_date_path(&opt, &blob("15", "02")));
+        assert!(filter_file_by_date_path(&opt, &blob("15", "03")));
+        assert!(filter_file_by_date_path(&opt, &blob("15", "05")));
+        assert!(!filter_file_by_date_path(&opt, &blob("15", "06")));
+        assert!(!filter_file_by_date_path(&opt, &blob("16", "04")));
+    }
+
+    #[test]
+    fn test_filter_file_event_hub_capture_path() {
+        let opt = FileDateOption {
+            file_date_from: Some("2024011503".to_string()),
+            file_date_to: None,
+        };
+        assert!(filter_file_by_date_path(
+            &opt,
+            "capture/ns/insights-operational-logs/0/2024/01/15/03/00/00.json"
+        ));
+        assert!(!filter_file_by_date_path(
+            &opt,
+            "capture/ns/insights-operational-logs/0/2024/01/15/02/59/59.json"
+        ));
+    }
+
+    #[test]
+    fn test_filter_file_hour_bound_keeps_day_only_path_of_that_day() {
+        let opt = FileDateOption {
+            file_date_from: Some("2024011512".to_string()),
+            file_date_to: Some("2024011512".to_string()),
+        };
+        let path = "AWSLogs/123/CloudTrail/us-east-1/2024/01/%s/123_CloudTrail_us-east-1_20240115T1200Z_x.json.gz";
+        assert!(filter_file_by_date_path(&opt, &path.replace("%s", "15")));
+        assert!(!filter_file_by_date_path(&opt, &path.replace("%s", "16")));
+    }
+
+    #[test]
+    fn test_filter_prefix_by_partial_date() {
+        let opt = FileDateOption {
+            file_date_from: Some("2024011503".to_string()),
+            file_date_to: Some("2024020100".to_string()),
+        };
+        let root = "s3://bucket/AWSLogs/123456789012/CloudTrail/us-east-1/";
+        assert!(filter_prefix_by_date_path(&opt, root));
+        assert!(!filter_prefix_by_date_path(&opt, &format!("{root}2023/")));
+        assert!(filter_prefix_by_date_path(&opt, &format!("{root}2024/")));
+        assert!(!filter_prefix_by_date_path(
+            &opt,
+            &format!("{root}2024/03/")
+        ));
+        assert!(filter_prefix_by_date_path(
+            &opt,
+            &format!("{root}2024/01/15/")
+        ));
+        assert!(!filter_prefix_by_date_path(
+            &opt,
+            &format!("{root}2024/01/14/")
+        ));
+        assert!(filter_prefix_by_date_path(
+            &opt,
+            &format!("{root}2024/02/01/")
+        ));
+        let azure = "az://insights-logs-auditlogs/resourceId=/tenants/abc/";
+        assert!(!filter_prefix_by_date_path(
+            &opt,
+            &format!("{azure}y=2024/m=01/d=15/h=02/")
+        ));
+        assert!(filter_prefix_by_date_path(
+            &opt,
+            &format!("{azure}y=2024/m=01/d=15/h=03/")
+        ));
+        // Deeper levels below a matching day are kept and left to the file filter.
+        assert!(filter_prefix_by_date_path(
+            &opt,
+            &format!("{root}2024/01/15/03/00/")
+        ));
+    }
+
     // --- filter_by_time tests ---
 
     #[test]
@@ -349,6 +522,20 @@ mod tests {
         assert!(filter_by_time(&opt, &value, "eventTimestamp"));
     }
 
+    #[test]
+    fn test_filter_by_time_nested_timestamp_key() {
+        // Google Workspace activities carry their timestamp at `id.time`.
+        let opt = TimeOption {
+            timeline_start: Some("2024-03-01T09:00:00Z".to_string()),
+            timeline_end: Some("2024-03-01T10:00:00Z".to_string()),
+            time_offset: None,
+        };
+        let value = json!({ "id": { "time": "2024-03-01T09:15:00.000Z" } });
+        assert!(filter_by_time(&opt, &value, ".id.time"));
+        let value = json!({ "id": { "time": "2024-03-01T11:15:00.000Z" } });
+        assert!(!filter_by_time(&opt, &value, ".id.time"));
+    }
+
     #[test]
     fn parse_offset_accepts_valid_offsets() {
         assert_eq!(parse_offset("30d"), Some(Duration::days(30)));